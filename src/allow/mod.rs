@@ -0,0 +1,169 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::net::IpAddr;
+use std::path::Path;
+
+use net::{self, Cidr};
+
+// Addresses and networks that must never be blocked, kept in a binary
+// prefix trie per address family so lookups cost at most one step per
+// prefix bit regardless of how many entries are loaded.
+pub struct Allowlist {
+    v4:  Node,
+    v6:  Node,
+    len: usize,
+}
+
+struct Node {
+    end:  bool,
+    next: [Option<Box<Node>>; 2],
+}
+
+impl Allowlist {
+    pub fn new() -> Allowlist {
+        Allowlist {
+            v4:  Node::new(),
+            v6:  Node::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, cidr: Cidr) {
+        if self.covers(cidr) {
+            return;
+        }
+
+        let pruned = {
+            let octets = net::octets(cidr.addr());
+            let mut node = self.root_mut(cidr.addr());
+
+            for n in 0..cidr.len() as usize {
+                let cur  = node;
+                let next = &mut cur.next[bit(&octets, n)];
+                if next.is_none() {
+                    *next = Some(Box::new(Node::new()));
+                }
+                node = match *next {
+                    Some(ref mut next) => &mut **next,
+                    None               => unreachable!(),
+                };
+            }
+
+            let pruned = node.next.iter().fold(0, |n, next| n + next.as_ref().map_or(0, |next| next.count()));
+            node.end  = true;
+            node.next = [None, None];
+            pruned
+        };
+
+        self.len = self.len + 1 - pruned;
+    }
+
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, Error> {
+        let file = try!(File::open(path));
+        let mut n = 0;
+
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line  = try!(line);
+            let entry = line.splitn(2, '#').next().unwrap().trim();
+            if entry.is_empty() {
+                continue;
+            }
+            match entry.parse() {
+                Ok(cidr) => self.insert(cidr),
+                Err(e)   => {
+                    let msg = format!("line {}: {}: {}", index + 1, entry, e);
+                    return Err(Error::new(ErrorKind::InvalidInput, msg));
+                },
+            }
+            n += 1;
+        }
+
+        Ok(n)
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        self.overlaps(Cidr::host(addr))
+    }
+
+    // True when an allowed entry contains the network or lies within it,
+    // so blocking a prefix can't take an allowed host down with it.
+    pub fn overlaps(&self, cidr: Cidr) -> bool {
+        let octets = net::octets(cidr.addr());
+        let mut node = self.root(cidr.addr());
+
+        for n in 0..cidr.len() as usize {
+            if node.end {
+                return true;
+            }
+            node = match node.next[bit(&octets, n)] {
+                Some(ref next) => next,
+                None           => return false,
+            };
+        }
+
+        node.end || node.next.iter().any(|next| next.is_some())
+    }
+
+    // True when a single allowed entry contains the whole network.
+    pub fn covers(&self, cidr: Cidr) -> bool {
+        let octets = net::octets(cidr.addr());
+        let mut node = self.root(cidr.addr());
+
+        for n in 0..cidr.len() as usize {
+            if node.end {
+                return true;
+            }
+            node = match node.next[bit(&octets, n)] {
+                Some(ref next) => next,
+                None           => return false,
+            };
+        }
+
+        node.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn root(&self, addr: IpAddr) -> &Node {
+        match addr {
+            IpAddr::V4(..) => &self.v4,
+            IpAddr::V6(..) => &self.v6,
+        }
+    }
+
+    fn root_mut(&mut self, addr: IpAddr) -> &mut Node {
+        match addr {
+            IpAddr::V4(..) => &mut self.v4,
+            IpAddr::V6(..) => &mut self.v6,
+        }
+    }
+}
+
+impl Node {
+    fn new() -> Node {
+        Node {
+            end:  false,
+            next: [None, None],
+        }
+    }
+
+    fn count(&self) -> usize {
+        let n = self.end as usize;
+        self.next.iter().fold(n, |n, next| n + next.as_ref().map_or(0, |next| next.count()))
+    }
+}
+
+fn bit(octets: &[u8], n: usize) -> usize {
+    (octets[n / 8] >> (7 - n % 8)) as usize & 1
+}
+
+#[cfg(test)]
+mod tests;
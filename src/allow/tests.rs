@@ -0,0 +1,85 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fs::File;
+use std::io::Write;
+use std::net::IpAddr;
+use tempdir::TempDir;
+use net::Cidr;
+use super::*;
+
+#[test]
+fn contains_host() {
+    let mut allow = Allowlist::new();
+    allow.insert(cidr("193.107.17.72"));
+
+    assert!(allow.contains(addr("193.107.17.72")));
+    assert!(!allow.contains(addr("193.107.17.73")));
+    assert!(!allow.contains(addr("::ffff:193.107.17.72")));
+}
+
+#[test]
+fn contains_network() {
+    let mut allow = Allowlist::new();
+    allow.insert(cidr("10.0.0.0/8"));
+    allow.insert(cidr("2001:db8::/32"));
+
+    assert!(allow.contains(addr("10.1.2.3")));
+    assert!(allow.contains(addr("2001:db8::1")));
+    assert!(!allow.contains(addr("11.0.0.1")));
+    assert!(!allow.contains(addr("2001:db9::1")));
+}
+
+#[test]
+fn overlaps() {
+    let mut allow = Allowlist::new();
+    allow.insert(cidr("193.107.17.72"));
+    allow.insert(cidr("10.0.0.0/8"));
+
+    assert!(allow.overlaps(cidr("193.107.17.0/24")));
+    assert!(allow.overlaps(cidr("10.1.0.0/16")));
+    assert!(allow.overlaps(cidr("0.0.0.0/0")));
+    assert!(!allow.overlaps(cidr("193.107.18.0/24")));
+    assert!(!allow.overlaps(cidr("::/0")));
+}
+
+#[test]
+fn insert_covered() {
+    let mut allow = Allowlist::new();
+    allow.insert(cidr("10.1.2.3"));
+    allow.insert(cidr("10.0.0.0/8"));
+    allow.insert(cidr("10.4.0.0/16"));
+    allow.insert(cidr("192.0.2.0/24"));
+
+    assert_eq!(2, allow.len());
+    assert!(allow.contains(addr("10.9.9.9")));
+}
+
+#[test]
+fn load() {
+    let dir  = TempDir::new("test").unwrap();
+    let path = dir.path().join("allow");
+    write!(File::create(&path).unwrap(), "# office\n198.51.100.0/24\n\n2001:db8::7 # vpn\n").unwrap();
+
+    let mut allow = Allowlist::new();
+    assert_eq!(2, allow.load(&path).unwrap());
+    assert!(allow.contains(addr("198.51.100.20")));
+    assert!(allow.contains(addr("2001:db8::7")));
+}
+
+#[test]
+fn load_invalid() {
+    let dir  = TempDir::new("test").unwrap();
+    let path = dir.path().join("allow");
+    write!(File::create(&path).unwrap(), "198.51.100.0/24\n198.51.100.1/24\n").unwrap();
+
+    let err = Allowlist::new().load(&path).unwrap_err();
+    assert_eq!("line 2: 198.51.100.1/24: host bits set in network address", err.to_string());
+}
+
+fn cidr(s: &str) -> Cidr {
+    s.parse().unwrap()
+}
+
+fn addr(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
@@ -0,0 +1,134 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use chrono::{DateTime, Duration, UTC};
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use net::Cidr;
+
+pub struct Bans {
+    expires: HashMap<Cidr, Option<DateTime<UTC>>>,
+    started: HashMap<Cidr, DateTime<UTC>>,
+    queue:   BTreeMap<DateTime<UTC>, Vec<Cidr>>,
+    history: HashMap<Cidr, Offense>,
+    decay:   Duration,
+    purged:  DateTime<UTC>,
+}
+
+// `last` is when the latest ban started, or ended once it has expired.
+#[derive(Clone, Copy, Debug)]
+pub struct Offense {
+    pub count: u32,
+    pub last:  DateTime<UTC>,
+}
+
+impl Bans {
+    pub fn new(decay: Duration) -> Bans {
+        Bans {
+            expires: HashMap::new(),
+            started: HashMap::new(),
+            queue:   BTreeMap::new(),
+            history: HashMap::new(),
+            decay:   decay,
+            purged:  UTC::now(),
+        }
+    }
+
+    pub fn insert(&mut self, cidr: Cidr, now: DateTime<UTC>, ban: Option<Duration>) {
+        self.schedule(cidr, ban.map(|d| now + d));
+        self.start(cidr, now);
+        let count = self.offenses(&cidr, now) + 1;
+        self.history.insert(cidr, Offense { count: count, last: now });
+    }
+
+    pub fn schedule(&mut self, cidr: Cidr, expires: Option<DateTime<UTC>>) {
+        if let Some(when) = expires {
+            self.queue.entry(when).or_insert(Vec::new()).push(cidr);
+        }
+        self.expires.insert(cidr, expires);
+    }
+
+    pub fn start(&mut self, cidr: Cidr, when: DateTime<UTC>) {
+        self.started.insert(cidr, when);
+    }
+
+    pub fn started(&self, cidr: &Cidr) -> Option<DateTime<UTC>> {
+        self.started.get(cidr).cloned()
+    }
+
+    pub fn record(&mut self, cidr: Cidr, offense: Offense) {
+        self.history.insert(cidr, offense);
+    }
+
+    pub fn offenses(&self, cidr: &Cidr, now: DateTime<UTC>) -> u32 {
+        match self.history.get(cidr) {
+            Some(offense) if now - offense.last <= self.decay => offense.count,
+            _                                                 => 0,
+        }
+    }
+
+    pub fn remove(&mut self, cidr: &Cidr) -> bool {
+        self.started.remove(cidr);
+        self.expires.remove(cidr).is_some()
+    }
+
+    pub fn contains(&self, cidr: &Cidr) -> bool {
+        self.expires.contains_key(cidr)
+    }
+
+    pub fn expires(&self, cidr: &Cidr) -> Option<Option<DateTime<UTC>>> {
+        self.expires.get(cidr).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.expires.len()
+    }
+
+    pub fn entries(&self) -> Vec<(Cidr, Option<DateTime<UTC>>)> {
+        self.expires.iter().map(|(cidr, expires)| (*cidr, *expires)).collect()
+    }
+
+    pub fn history(&self) -> Vec<(Cidr, Offense)> {
+        self.history.iter().map(|(cidr, offense)| (*cidr, *offense)).collect()
+    }
+
+    pub fn expire(&mut self, now: DateTime<UTC>) -> Vec<Cidr> {
+        let due: Vec<_> = self.queue.keys().take_while(|when| **when <= now).cloned().collect();
+        let mut expired = Vec::new();
+
+        for when in due {
+            for cidr in self.queue.remove(&when).unwrap_or(Vec::new()) {
+                // entries rescheduled since being queued are left alone
+                if self.expires.get(&cidr) == Some(&Some(when)) {
+                    self.expires.remove(&cidr);
+                    self.started.remove(&cidr);
+                    expired.push(cidr);
+                    // decay counts from the end of the ban, not its start
+                    if let Some(offense) = self.history.get_mut(&cidr) {
+                        offense.last = cmp::max(offense.last, when);
+                    }
+                }
+            }
+        }
+
+        if now - self.purged > Duration::minutes(1) {
+            self.purge(now);
+        }
+
+        expired
+    }
+
+    fn purge(&mut self, now: DateTime<UTC>) {
+        let decay = self.decay;
+        let stale: Vec<_> = self.history.iter().filter(|&(cidr, offense)| {
+            now - offense.last > decay && !self.expires.contains_key(cidr)
+        }).map(|(cidr, _)| *cidr).collect();
+
+        for cidr in stale {
+            self.history.remove(&cidr);
+        }
+        self.purged = now;
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,96 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use chrono::{Duration, UTC};
+use net::Cidr;
+use super::Bans;
+
+#[test]
+fn expire_due() {
+    let now = UTC::now();
+    let mut bans = Bans::new(Duration::days(1));
+    let a = cidr("192.0.2.1");
+    let b = cidr("192.0.2.2");
+
+    bans.insert(a, now, Some(Duration::minutes(1)));
+    bans.insert(b, now, Some(Duration::minutes(2)));
+
+    assert!(bans.expire(now).is_empty());
+    assert_eq!(vec![a], bans.expire(now + Duration::minutes(1)));
+    assert!(!bans.contains(&a) && bans.contains(&b));
+    assert_eq!(vec![b], bans.expire(now + Duration::minutes(5)));
+    assert_eq!(0, bans.len());
+}
+
+#[test]
+fn expire_never() {
+    let now = UTC::now();
+    let mut bans = Bans::new(Duration::days(1));
+    let a = cidr("2001:db8::1");
+
+    bans.insert(a, now, None);
+    assert!(bans.expire(now + Duration::weeks(52)).is_empty());
+    assert!(bans.contains(&a));
+}
+
+#[test]
+fn expire_rescheduled() {
+    let now = UTC::now();
+    let mut bans = Bans::new(Duration::days(1));
+    let a = cidr("192.0.2.1");
+
+    bans.insert(a, now, Some(Duration::minutes(1)));
+    bans.insert(a, now, Some(Duration::minutes(10)));
+
+    assert!(bans.expire(now + Duration::minutes(1)).is_empty());
+    assert_eq!(vec![a], bans.expire(now + Duration::minutes(10)));
+}
+
+#[test]
+fn offense_history() {
+    let now = UTC::now();
+    let mut bans = Bans::new(Duration::days(1));
+    let a = cidr("192.0.2.1");
+
+    assert_eq!(0, bans.offenses(&a, now));
+    bans.insert(a, now, Some(Duration::minutes(10)));
+    assert_eq!(1, bans.offenses(&a, now));
+
+    let later = now + Duration::hours(1);
+    bans.expire(later);
+    bans.insert(a, later, Some(Duration::minutes(10)));
+    assert_eq!(2, bans.offenses(&a, later));
+}
+
+#[test]
+fn offense_decay() {
+    let now = UTC::now();
+    let mut bans = Bans::new(Duration::days(1));
+    let a = cidr("192.0.2.1");
+
+    bans.insert(a, now, Some(Duration::minutes(10)));
+    bans.expire(now + Duration::days(2));
+
+    assert_eq!(0, bans.offenses(&a, now + Duration::days(2)));
+    assert!(bans.history.is_empty());
+}
+
+#[test]
+fn offense_decay_after_ban() {
+    let now = UTC::now();
+    let mut bans = Bans::new(Duration::days(1));
+    let a = cidr("192.0.2.1");
+
+    bans.insert(a, now, Some(Duration::days(2)));
+    bans.expire(now + Duration::days(1) + Duration::hours(1));
+    assert!(!bans.history.is_empty());
+
+    let later = now + Duration::days(2) + Duration::hours(1);
+    bans.expire(later);
+    assert_eq!(1, bans.offenses(&a, later));
+    bans.insert(a, later, Some(Duration::days(2)));
+    assert_eq!(2, bans.offenses(&a, later));
+}
+
+fn cidr(s: &str) -> Cidr {
+    Cidr::host(s.parse().unwrap())
+}
@@ -0,0 +1,105 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use chrono::Duration;
+use net::Cidr;
+use super::{Blocker, Stats};
+
+pub struct Memory {
+    tables:   HashMap<String, Vec<Cidr>>,
+    packets:  HashMap<Cidr, u64>,
+    timeouts: HashMap<Cidr, Option<Duration>>,
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory {
+            tables:   HashMap::new(),
+            packets:  HashMap::new(),
+            timeouts: HashMap::new(),
+        }
+    }
+
+    pub fn hit(&mut self, cidr: Cidr, packets: u64) {
+        *self.packets.entry(cidr).or_insert(0) += packets;
+    }
+
+    pub fn table(&self, table: &str) -> Option<&[Cidr]> {
+        self.tables.get(table).map(|addrs| &addrs[..])
+    }
+
+    // The timeout an address was added with, if it is in some table.
+    pub fn timeout(&self, cidr: &Cidr) -> Option<Option<Duration>> {
+        self.timeouts.get(cidr).cloned()
+    }
+
+    pub fn drop_table(&mut self, table: &str) -> bool {
+        self.tables.remove(table).is_some()
+    }
+
+    fn get(&mut self, table: &str) -> Result<&mut Vec<Cidr>, Error> {
+        match self.tables.get_mut(table) {
+            Some(addrs) => Ok(addrs),
+            None        => Err(Error::new(ErrorKind::NotFound, "table does not exist")),
+        }
+    }
+}
+
+impl Blocker for Memory {
+    fn ensure_table(&mut self, table: &str) -> Result<(), Error> {
+        self.tables.entry(table.to_string()).or_insert(Vec::new());
+        Ok(())
+    }
+
+    fn add(&mut self, table: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<usize, Error> {
+        let added = try!(self.add_each(table, addrs, timeout));
+        Ok(added.into_iter().filter(|added| *added).count())
+    }
+
+    fn add_each(&mut self, table: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<Vec<bool>, Error> {
+        let mut added = Vec::with_capacity(addrs.len());
+        for addr in addrs {
+            let new = {
+                let entries = try!(self.get(table));
+                let new = !entries.contains(addr);
+                if new {
+                    entries.push(*addr);
+                }
+                new
+            };
+            if new {
+                self.timeouts.insert(*addr, timeout);
+            }
+            added.push(new);
+        }
+        Ok(added)
+    }
+
+    fn remove(&mut self, table: &str, addrs: &[Cidr]) -> Result<usize, Error> {
+        let len = {
+            let entries = try!(self.get(table));
+            let len = entries.len();
+            entries.retain(|addr| !addrs.contains(addr));
+            len - entries.len()
+        };
+        for addr in addrs {
+            self.timeouts.remove(addr);
+        }
+        Ok(len)
+    }
+
+    fn list(&mut self, table: &str) -> Result<Vec<Cidr>, Error> {
+        Ok(try!(self.get(table)).clone())
+    }
+
+    fn stats(&mut self, table: &str) -> Result<Vec<Stats>, Error> {
+        let entries = try!(self.list(table));
+        Ok(entries.into_iter().map(|cidr| Stats {
+            cidr:    cidr,
+            packets: self.packets.get(&cidr).cloned().unwrap_or(0),
+            bytes:   0,
+            added:   None,
+        }).collect())
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+mod memory;
+
+use std::io::Error;
+use chrono::{DateTime, Duration, UTC};
+use net::Cidr;
+
+pub use self::memory::Memory;
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Changes {
+    pub added:   usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stats {
+    pub cidr:    Cidr,
+    pub packets: u64,
+    pub bytes:   u64,
+    pub added:   Option<DateTime<UTC>>,
+}
+
+pub trait Blocker {
+    fn ensure_table(&mut self, table: &str) -> Result<(), Error>;
+    fn add(&mut self, table: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<usize, Error>;
+    fn remove(&mut self, table: &str, addrs: &[Cidr]) -> Result<usize, Error>;
+    fn list(&mut self, table: &str) -> Result<Vec<Cidr>, Error>;
+
+    // Add addresses, reporting for each whether it was newly added.
+    // Firewalls that can say so for a whole batch should override this.
+    fn add_each(&mut self, table: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<Vec<bool>, Error> {
+        let mut added = Vec::with_capacity(addrs.len());
+        for cidr in addrs {
+            added.push(try!(self.add(table, &[*cidr], timeout)) == 1);
+        }
+        Ok(added)
+    }
+
+    // Make the table hold exactly these addresses, each added with the
+    // time remaining on its ban. Firewalls that can swap a table's contents
+    // atomically should override this.
+    fn replace(&mut self, table: &str, addrs: &[(Cidr, Option<Duration>)]) -> Result<Changes, Error> {
+        let present = try!(self.list(table));
+        let stale: Vec<Cidr> = present.into_iter().filter(|cidr| !addrs.iter().any(|&(c, _)| c == *cidr)).collect();
+        let removed = try!(self.remove(table, &stale));
+        let mut added = 0;
+        for &(cidr, timeout) in addrs {
+            added += try!(self.add(table, &[cidr], timeout));
+        }
+        Ok(Changes { added: added, removed: removed, changed: 0 })
+    }
+
+    // Traffic blocked by each entry, where the firewall counts it.
+    fn stats(&mut self, _table: &str) -> Result<Vec<Stats>, Error> {
+        Ok(Vec::new())
+    }
+
+    // Drop established connections from the addresses, returning how many
+    // were killed. Only firewalls that track connection state support this.
+    fn kill_states(&mut self, _addrs: &[Cidr]) -> Result<usize, Error> {
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,41 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use chrono::Duration;
+use net::Cidr;
+use super::{Blocker, Changes, Memory};
+
+#[test]
+fn replace() {
+    let mut blocker = Memory::new();
+    blocker.ensure_table("irongate").unwrap();
+    blocker.add("irongate", &[cidr("192.0.2.1"), cidr("192.0.2.2")], None).unwrap();
+
+    let changes = blocker.replace("irongate", &[(cidr("192.0.2.2"), None), (cidr("198.51.100.0/24"), None)]).unwrap();
+    assert_eq!(Changes { added: 1, removed: 1, changed: 0 }, changes);
+    assert_eq!(Some(&[cidr("192.0.2.2"), cidr("198.51.100.0/24")][..]), blocker.table("irongate"));
+}
+
+#[test]
+fn replace_empty() {
+    let mut blocker = Memory::new();
+    blocker.ensure_table("irongate").unwrap();
+    blocker.add("irongate", &[cidr("192.0.2.1")], None).unwrap();
+
+    assert_eq!(1, blocker.replace("irongate", &[]).unwrap().removed);
+    assert_eq!(Some(&[][..]), blocker.table("irongate"));
+}
+
+#[test]
+fn replace_timeouts() {
+    let mut blocker = Memory::new();
+    blocker.ensure_table("irongate").unwrap();
+
+    let ten = Some(Duration::minutes(10));
+    blocker.replace("irongate", &[(cidr("192.0.2.1"), ten), (cidr("192.0.2.2"), None)]).unwrap();
+    assert_eq!(Some(ten), blocker.timeout(&cidr("192.0.2.1")));
+    assert_eq!(Some(None), blocker.timeout(&cidr("192.0.2.2")));
+}
+
+fn cidr(s: &str) -> Cidr {
+    s.parse().unwrap()
+}
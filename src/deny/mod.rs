@@ -0,0 +1,193 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use chrono::Duration;
+
+use block::Blocker;
+use net::{self, Cidr};
+use store::write_atomic;
+
+// Keeps banned addresses in a section of a TCP wrappers file delimited
+// by marker comments; every line outside the section is preserved.
+pub struct HostsDeny {
+    path:    PathBuf,
+    daemons: String,
+}
+
+pub struct Section {
+    pub before:  Vec<String>,
+    pub entries: Vec<Cidr>,
+    pub after:   Vec<String>,
+    pub found:   bool,
+}
+
+impl HostsDeny {
+    pub fn new<P: AsRef<Path>>(path: P, daemons: &str) -> HostsDeny {
+        HostsDeny {
+            path:    path.as_ref().to_path_buf(),
+            daemons: daemons.to_string(),
+        }
+    }
+
+    fn read(&self, table: &str) -> Result<Section, Error> {
+        let mut text = String::new();
+        match File::open(&self.path) {
+            Ok(mut file)                                  => try!(file.read_to_string(&mut text)),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => 0,
+            Err(e)                                        => return Err(e),
+        };
+        Section::parse(&text, table)
+    }
+
+    fn write(&self, table: &str, section: &Section) -> Result<(), Error> {
+        let text = section.render(table, &self.daemons);
+        write_atomic(&self.path, text.as_bytes())
+    }
+}
+
+impl Blocker for HostsDeny {
+    fn ensure_table(&mut self, table: &str) -> Result<(), Error> {
+        let mut section = try!(self.read(table));
+        if !section.found {
+            section.found = true;
+            try!(self.write(table, &section));
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, table: &str, addrs: &[Cidr], _: Option<Duration>) -> Result<usize, Error> {
+        let mut section = try!(self.read(table));
+        let mut n = 0;
+        for addr in addrs {
+            if !section.entries.contains(addr) {
+                section.entries.push(*addr);
+                n += 1;
+            }
+        }
+        if n > 0 {
+            section.found = true;
+            try!(self.write(table, &section));
+        }
+        Ok(n)
+    }
+
+    fn remove(&mut self, table: &str, addrs: &[Cidr]) -> Result<usize, Error> {
+        let mut section = try!(self.read(table));
+        let len = section.entries.len();
+        section.entries.retain(|addr| !addrs.contains(addr));
+        let n = len - section.entries.len();
+        if n > 0 {
+            try!(self.write(table, &section));
+        }
+        Ok(n)
+    }
+
+    fn list(&mut self, table: &str) -> Result<Vec<Cidr>, Error> {
+        Ok(try!(self.read(table)).entries)
+    }
+}
+
+impl Section {
+    // A BEGIN marker without an END is an error rather than a section
+    // running to the end of the file, so lines after it aren't lost.
+    pub fn parse(text: &str, table: &str) -> Result<Section, Error> {
+        let (begin, end) = markers(table);
+        let mut section = Section {
+            before:  Vec::new(),
+            entries: Vec::new(),
+            after:   Vec::new(),
+            found:   false,
+        };
+
+        let mut inside = false;
+        for line in text.lines() {
+            match line.trim() {
+                marker if marker == begin && !section.found => {
+                    inside = true;
+                    section.found = true;
+                },
+                marker if marker == end && inside => inside = false,
+                _ if inside                       => section.entries.extend(entry(line).into_iter()),
+                _ if section.found                => section.after.push(line.to_string()),
+                _                                 => section.before.push(line.to_string()),
+            }
+        }
+
+        match inside {
+            true  => Err(Error::new(ErrorKind::InvalidData, format!("missing end marker for table {}", table))),
+            false => Ok(section),
+        }
+    }
+
+    pub fn render(&self, table: &str, daemons: &str) -> String {
+        let (begin, end) = markers(table);
+        let mut lines = self.before.clone();
+
+        if self.found {
+            lines.push(begin);
+            for cidr in &self.entries {
+                lines.push(format!("{}: {}", daemons, pattern(cidr)));
+            }
+            lines.push(end);
+        }
+
+        lines.extend(self.after.iter().cloned());
+
+        let mut text = lines.connect("\n");
+        text.push('\n');
+        text
+    }
+}
+
+fn markers(table: &str) -> (String, String) {
+    (format!("# BEGIN irongate {}", table), format!("# END irongate {}", table))
+}
+
+// TCP wrappers match IPv4 networks as addr/netmask and IPv6 networks
+// as [addr]/len.
+pub fn pattern(cidr: &Cidr) -> String {
+    match (cidr.addr(), cidr.is_host()) {
+        (IpAddr::V4(addr), true)  => format!("{}", addr),
+        (IpAddr::V4(addr), false) => format!("{}/{}", addr, netmask(cidr.len())),
+        (IpAddr::V6(addr), true)  => format!("[{}]", addr),
+        (IpAddr::V6(addr), false) => format!("[{}]/{}", addr, cidr.len()),
+    }
+}
+
+pub fn entry(line: &str) -> Option<Cidr> {
+    let pattern = match line.splitn(2, ':').nth(1) {
+        Some(pattern) => pattern.trim(),
+        None          => return None,
+    };
+
+    let mut parts = pattern.splitn(2, '/');
+    let addr = parts.next().unwrap().trim_matches(|c: char| c == '[' || c == ']');
+    let addr: IpAddr = match addr.parse() {
+        Ok(addr) => addr,
+        Err(..)  => return None,
+    };
+
+    let len = match (addr, parts.next()) {
+        (_,              None)       => net::max_len(addr),
+        (IpAddr::V4(..), Some(mask)) => match mask.parse::<IpAddr>() {
+            Ok(mask) => net::octets(mask).iter().map(|b| b.count_ones()).fold(0, |a, b| a + b) as u8,
+            Err(..)  => return None,
+        },
+        (IpAddr::V6(..), Some(len))  => match len.parse() {
+            Ok(len)  => len,
+            Err(..)  => return None,
+        },
+    };
+
+    Some(Cidr::new(addr, len))
+}
+
+fn netmask(len: u8) -> IpAddr {
+    Cidr::new(IpAddr::V4(Ipv4Addr::new(255, 255, 255, 255)), len).addr()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,114 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use tempdir::TempDir;
+
+use block::Blocker;
+use net::Cidr;
+use super::*;
+
+#[test]
+fn patterns() {
+    let entries = [
+        ("192.0.2.1",      "192.0.2.1"),
+        ("203.0.113.0/24", "203.0.113.0/255.255.255.0"),
+        ("2001:db8::1",    "[2001:db8::1]"),
+        ("2001:db8::/48",  "[2001:db8::]/48"),
+    ];
+
+    for &(cidr, pat) in &entries {
+        let cidr: Cidr = cidr.parse().unwrap();
+        assert_eq!(pat, pattern(&cidr));
+        assert_eq!(Some(cidr), entry(&format!("sshd: {}", pat)));
+    }
+
+    assert_eq!(None, entry("sshd: LOCAL"));
+    assert_eq!(None, entry("# comment"));
+}
+
+#[test]
+fn preserve_outside_section() {
+    let dir = TempDir::new("test").unwrap();
+    let path = dir.path().join("hosts.deny");
+    write!(&mut File::create(&path).unwrap(), "# local policy\nALL: 198.51.100.9\n").unwrap();
+
+    let mut deny = HostsDeny::new(&path, "sshd");
+    let a: Cidr = "192.0.2.1".parse().unwrap();
+    let b: Cidr = "2001:db8::/48".parse().unwrap();
+
+    deny.ensure_table("irongate").unwrap();
+    assert_eq!(2, deny.add("irongate", &[a, b], None).unwrap());
+    assert_eq!(0, deny.add("irongate", &[a], None).unwrap());
+
+    assert_eq!(read(&path), "\
+# local policy
+ALL: 198.51.100.9
+# BEGIN irongate irongate
+sshd: 192.0.2.1
+sshd: [2001:db8::]/48
+# END irongate irongate
+");
+
+    let mut text = read(&path);
+    text.push_str("ALL: 198.51.100.10\n");
+    write!(&mut File::create(&path).unwrap(), "{}", text).unwrap();
+
+    assert_eq!(1, deny.remove("irongate", &[a]).unwrap());
+    assert_eq!(vec![b], deny.list("irongate").unwrap());
+    assert_eq!(read(&path), "\
+# local policy
+ALL: 198.51.100.9
+# BEGIN irongate irongate
+sshd: [2001:db8::]/48
+# END irongate irongate
+ALL: 198.51.100.10
+");
+}
+
+#[test]
+fn missing_file() {
+    let dir = TempDir::new("test").unwrap();
+    let path = dir.path().join("hosts.deny");
+    let mut deny = HostsDeny::new(&path, "ALL");
+
+    assert!(deny.list("irongate").unwrap().is_empty());
+    deny.add("irongate", &["192.0.2.1".parse().unwrap()], None).unwrap();
+    assert_eq!("# BEGIN irongate irongate\nALL: 192.0.2.1\n# END irongate irongate\n", read(&path));
+}
+
+#[test]
+fn unterminated_section() {
+    let dir = TempDir::new("test").unwrap();
+    let path = dir.path().join("hosts.deny");
+    let text = "# BEGIN irongate irongate\nsshd: 192.0.2.1\nALL: PARANOID\n";
+    write!(&mut File::create(&path).unwrap(), "{}", text).unwrap();
+
+    let mut deny = HostsDeny::new(&path, "sshd");
+    assert!(deny.ensure_table("irongate").is_err());
+    assert!(deny.add("irongate", &["192.0.2.2".parse().unwrap()], None).is_err());
+    assert_eq!(text, read(&path));
+}
+
+#[test]
+#[cfg(unix)]
+fn preserve_mode() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new("test").unwrap();
+    let path = dir.path().join("hosts.deny");
+    File::create(&path).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+    let mut deny = HostsDeny::new(&path, "sshd");
+    deny.add("irongate", &["192.0.2.1".parse().unwrap()], None).unwrap();
+    assert_eq!(0o640, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+}
+
+fn read(path: &Path) -> String {
+    let mut s = String::new();
+    File::open(path).unwrap().read_to_string(&mut s).unwrap();
+    s
+}
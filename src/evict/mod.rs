@@ -0,0 +1,122 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+use chrono::{DateTime, UTC};
+
+use net::Cidr;
+
+// How to make room when the ban list reaches its cap.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Policy {
+    Oldest,
+    FewestHits,
+    Collapse,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Entry {
+    pub cidr:  Cidr,
+    pub added: DateTime<UTC>,
+    pub hits:  u64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Eviction {
+    Remove(Cidr),
+    Collapse(Cidr, Vec<Cidr>),
+}
+
+impl Eviction {
+    pub fn freed(&self) -> usize {
+        match *self {
+            Eviction::Remove(..)             => 1,
+            Eviction::Collapse(_, ref cidrs) => cidrs.len() - 1,
+        }
+    }
+}
+
+// Choose evictions freeing at least `excess` entries. Collapsing merges
+// hosts sharing the most populated IPv4 or IPv6 prefix of `lens` into
+// one entry, skipping prefixes `allowed` rejects, then removes the
+// oldest entries if merging alone isn't enough.
+pub fn plan<F>(policy: Policy, entries: &[Entry], excess: usize, lens: (u8, u8), allowed: F) -> Vec<Eviction> where F: Fn(Cidr) -> bool {
+    let mut entries = entries.to_vec();
+    let mut plan    = Vec::new();
+    let mut freed   = 0;
+
+    if policy == Policy::Collapse {
+        let mut groups: HashMap<Cidr, Vec<Cidr>> = HashMap::new();
+        for entry in &entries {
+            let prefix = prefix(entry.cidr, lens);
+            if prefix.len() < entry.cidr.len() {
+                groups.entry(prefix).or_insert(Vec::new()).push(entry.cidr);
+            }
+        }
+
+        let mut groups: Vec<(Cidr, Vec<Cidr>)> = groups.into_iter().filter(|&(ref prefix, ref cidrs)| {
+            cidrs.len() > 1 && allowed(*prefix)
+        }).collect();
+        groups.sort_by(|a, b| a.0.to_string().cmp(&b.0.to_string()));
+        groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+
+        for (prefix, mut cidrs) in groups {
+            if freed >= excess {
+                break;
+            }
+            cidrs.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+            entries.retain(|entry| !cidrs.contains(&entry.cidr));
+            freed += cidrs.len() - 1;
+            plan.push(Eviction::Collapse(prefix, cidrs));
+        }
+    }
+
+    entries.sort_by(|a, b| match policy {
+        Policy::FewestHits => (a.hits, a.added).cmp(&(b.hits, b.added)),
+        _                  => a.added.cmp(&b.added),
+    });
+
+    for entry in entries {
+        if freed >= excess {
+            break;
+        }
+        plan.push(Eviction::Remove(entry.cidr));
+        freed += 1;
+    }
+
+    plan
+}
+
+fn prefix(cidr: Cidr, (len4, len6): (u8, u8)) -> Cidr {
+    match cidr.addr() {
+        addr @ IpAddr::V4(..) => Cidr::new(addr, len4),
+        addr @ IpAddr::V6(..) => Cidr::new(addr, len6),
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Policy, String> {
+        match s {
+            "oldest"      => Ok(Policy::Oldest),
+            "fewest-hits" => Ok(Policy::FewestHits),
+            "collapse"    => Ok(Policy::Collapse),
+            _             => Err(format!("unknown eviction policy: {}", s)),
+        }
+    }
+}
+
+impl Display for Eviction {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Eviction::Remove(cidr)                => write!(fmt, "removed {}", cidr),
+            Eviction::Collapse(prefix, ref cidrs) => write!(fmt, "collapsed {} addresses into {}", cidrs.len(), prefix),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,97 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use chrono::{DateTime, Duration, UTC};
+use net::Cidr;
+use super::*;
+
+#[test]
+fn oldest() {
+    let entries = entries();
+    assert_eq!(vec![
+        Eviction::Remove(cidr("193.107.17.72")),
+        Eviction::Remove(cidr("193.107.17.73")),
+    ], plan(Policy::Oldest, &entries, 2, (24, 64), |_| true));
+}
+
+#[test]
+fn fewest_hits() {
+    let entries = entries();
+    assert_eq!(vec![
+        Eviction::Remove(cidr("2404:6800:4004:814::1")),
+        Eviction::Remove(cidr("193.107.17.73")),
+    ], plan(Policy::FewestHits, &entries, 2, (24, 64), |_| true));
+}
+
+#[test]
+fn collapse() {
+    let entries = entries();
+    assert_eq!(vec![
+        Eviction::Collapse(cidr("193.107.17.0/24"), vec![
+            cidr("193.107.17.72"),
+            cidr("193.107.17.73"),
+            cidr("193.107.17.74"),
+        ]),
+    ], plan(Policy::Collapse, &entries, 2, (24, 64), |_| true));
+}
+
+#[test]
+fn collapse_fallback() {
+    let entries = entries();
+    assert_eq!(vec![
+        Eviction::Collapse(cidr("193.107.17.0/24"), vec![
+            cidr("193.107.17.72"),
+            cidr("193.107.17.73"),
+            cidr("193.107.17.74"),
+        ]),
+        Eviction::Remove(cidr("2404:6800:4004:814::1")),
+    ], plan(Policy::Collapse, &entries, 3, (24, 64), |_| true));
+}
+
+#[test]
+fn collapse_disallowed() {
+    let entries = entries();
+    let allow   = cidr("193.107.17.0/24");
+    assert_eq!(vec![
+        Eviction::Remove(cidr("193.107.17.72")),
+    ], plan(Policy::Collapse, &entries, 1, (24, 64), |prefix| prefix != allow));
+}
+
+#[test]
+fn freed() {
+    assert_eq!(1, Eviction::Remove(cidr("193.107.17.72")).freed());
+    assert_eq!(2, Eviction::Collapse(cidr("193.107.17.0/24"), vec![
+        cidr("193.107.17.72"),
+        cidr("193.107.17.73"),
+        cidr("193.107.17.74"),
+    ]).freed());
+}
+
+#[test]
+fn parse_policy() {
+    assert_eq!(Ok(Policy::Oldest),     "oldest".parse());
+    assert_eq!(Ok(Policy::FewestHits), "fewest-hits".parse());
+    assert_eq!(Ok(Policy::Collapse),   "collapse".parse());
+    assert!("newest".parse::<Policy>().is_err());
+}
+
+fn entries() -> Vec<Entry> {
+    let now = UTC::now();
+    vec![
+        entry("193.107.17.72",         now - Duration::minutes(4), 9),
+        entry("193.107.17.73",         now - Duration::minutes(3), 2),
+        entry("2404:6800:4004:814::1", now - Duration::minutes(2), 1),
+        entry("193.107.17.74",         now - Duration::minutes(1), 5),
+    ]
+}
+
+fn entry(s: &str, added: DateTime<UTC>, hits: u64) -> Entry {
+    Entry {
+        cidr:  cidr(s),
+        added: added,
+        hits:  hits,
+    }
+}
+
+fn cidr(s: &str) -> Cidr {
+    s.parse().unwrap()
+}
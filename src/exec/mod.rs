@@ -0,0 +1,236 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+mod template;
+
+use std::cmp;
+use std::io::{Error, ErrorKind, Read};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use chrono::Duration;
+
+use libc::funcs::posix01::wait::waitpid;
+use libc::types::os::arch::c95::c_int;
+use libc::types::os::arch::posix88::pid_t;
+
+use block::Blocker;
+use net::Cidr;
+
+pub use self::template::Template;
+
+const WNOHANG:     c_int = 1;
+const MAX_BACKOFF: u32   = 60000;
+
+#[derive(Clone)]
+pub struct Config {
+    pub ban:       String,
+    pub unban:     Option<String>,
+    pub list:      Option<String>,
+    pub timeout:   u32,
+    pub retries:   u32,
+    pub backoff:   u32,
+    pub jobs:      usize,
+    pub unchanged: Option<i32>,
+}
+
+pub struct Exec {
+    ban:    Template,
+    unban:  Option<Template>,
+    list:   Option<Template>,
+    policy: Policy,
+    jobs:   usize,
+}
+
+#[derive(Clone, Copy)]
+struct Policy {
+    timeout:   u32,
+    retries:   u32,
+    backoff:   u32,
+    unchanged: Option<i32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Status {
+    Exit(i32),
+    Signal(i32),
+    Timeout,
+}
+
+impl Exec {
+    pub fn new(config: Config) -> Result<Exec, Error> {
+        Ok(Exec {
+            ban:    try!(Template::parse(&config.ban)),
+            unban:  match config.unban {
+                Some(ref s) => Some(try!(Template::parse(s))),
+                None        => None,
+            },
+            list:   match config.list {
+                Some(ref s) => Some(try!(Template::parse(s))),
+                None        => None,
+            },
+            policy: Policy {
+                timeout:   config.timeout,
+                retries:   config.retries,
+                backoff:   config.backoff,
+                unchanged: config.unchanged,
+            },
+            jobs:   if config.jobs > 0 { config.jobs } else { 1 },
+        })
+    }
+
+    // Run one command per address, at most `jobs` at a time, and count
+    // the commands that reported a change.
+    fn each(&self, template: &Template, table: &str, addrs: &[Cidr], ban: Option<Duration>) -> Result<usize, Error> {
+        let mut changed = 0;
+        let mut failed  = None;
+
+        for chunk in addrs.chunks(self.jobs) {
+            let threads: Vec<_> = chunk.iter().map(|cidr| {
+                let argv   = template.render(&vars(table, cidr, ban));
+                let policy = self.policy;
+                thread::spawn(move || attempt(&argv, policy))
+            }).collect();
+
+            for thread in threads {
+                match thread.join() {
+                    Ok(Ok(true))  => changed += 1,
+                    Ok(Ok(false)) => (),
+                    Ok(Err(e))    => failed = Some(e),
+                    Err(..)       => failed = Some(Error::new(ErrorKind::Other, "command thread panicked")),
+                }
+            }
+        }
+
+        match failed {
+            Some(e) => Err(e),
+            None    => Ok(changed),
+        }
+    }
+}
+
+impl Blocker for Exec {
+    fn ensure_table(&mut self, _: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn add(&mut self, table: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<usize, Error> {
+        self.each(&self.ban, table, addrs, timeout)
+    }
+
+    fn remove(&mut self, table: &str, addrs: &[Cidr]) -> Result<usize, Error> {
+        match self.unban {
+            Some(ref unban) => self.each(unban, table, addrs, None),
+            None            => Err(Error::new(ErrorKind::InvalidInput, "no unban command configured")),
+        }
+    }
+
+    fn list(&mut self, table: &str) -> Result<Vec<Cidr>, Error> {
+        let list = match self.list {
+            Some(ref list) => list,
+            None           => return Ok(Vec::new()),
+        };
+
+        let argv = list.render(&[("table", table.to_string())]);
+        let (status, output) = try!(run(&argv, self.policy.timeout, true));
+        match status {
+            Status::Exit(0) => Ok(output.lines().filter_map(|line| line.trim().parse().ok()).collect()),
+            status          => Err(failure(&argv, status)),
+        }
+    }
+}
+
+fn vars(table: &str, cidr: &Cidr, ban: Option<Duration>) -> Vec<(&'static str, String)> {
+    vec![
+        ("addr",     cidr.addr().to_string()),
+        ("prefix",   cidr.len().to_string()),
+        ("cidr",     format!("{}/{}", cidr.addr(), cidr.len())),
+        ("table",    table.to_string()),
+        ("duration", ban.map(|d| d.num_seconds()).unwrap_or(0).to_string()),
+    ]
+}
+
+// Run a command until it exits with status 0, or with the configured
+// unchanged status, retrying other failures with exponential backoff.
+fn attempt(argv: &[String], policy: Policy) -> Result<bool, Error> {
+    let mut delay = policy.backoff;
+    let mut tries = 0;
+
+    loop {
+        let status = match run(argv, policy.timeout, false) {
+            Ok((status, _)) => status,
+            Err(e)          => return Err(e),
+        };
+
+        match status {
+            Status::Exit(0)                                => return Ok(true),
+            Status::Exit(n) if Some(n) == policy.unchanged => return Ok(false),
+            status if tries >= policy.retries              => return Err(failure(argv, status)),
+            _                                              => (),
+        }
+
+        thread::sleep_ms(delay);
+        delay = cmp::min(delay * 2, MAX_BACKOFF);
+        tries += 1;
+    }
+}
+
+pub fn run(argv: &[String], timeout: u32, capture: bool) -> Result<(Status, String), Error> {
+    let stdout = if capture { Stdio::piped() } else { Stdio::null() };
+    let mut child = try!(Command::new(&argv[0]).args(&argv[1..])
+                         .stdin(Stdio::null()).stdout(stdout).stderr(Stdio::null()).spawn());
+
+    let reader = child.stdout.take().map(|mut out| thread::spawn(move || {
+        let mut s = String::new();
+        out.read_to_string(&mut s).map(|_| s)
+    }));
+
+    let status = try!(wait(&mut child, timeout));
+
+    let output = match reader.map(|r| r.join()) {
+        Some(Ok(Ok(s))) => s,
+        _               => String::new(),
+    };
+
+    Ok((status, output))
+}
+
+fn wait(child: &mut Child, timeout: u32) -> Result<Status, Error> {
+    let pid = child.id() as pid_t;
+    let mut status: c_int = 0;
+    let mut waited = 0;
+
+    loop {
+        match unsafe { waitpid(pid, &mut status, WNOHANG) } {
+            -1 => return Err(Error::last_os_error()),
+             0 => (),
+             _ => return Ok(decode(status)),
+        }
+
+        if waited >= timeout {
+            try!(child.kill());
+            unsafe { waitpid(pid, &mut status, 0) };
+            return Ok(Status::Timeout);
+        }
+
+        thread::sleep_ms(10);
+        waited += 10;
+    }
+}
+
+fn decode(status: c_int) -> Status {
+    match status & 0x7f {
+        0   => Status::Exit((status >> 8) & 0xff),
+        sig => Status::Signal(sig),
+    }
+}
+
+fn failure(argv: &[String], status: Status) -> Error {
+    let reason = match status {
+        Status::Exit(n)   => format!("exited with status {}", n),
+        Status::Signal(n) => format!("killed by signal {}", n),
+        Status::Timeout   => "timed out".to_string(),
+    };
+    Error::new(ErrorKind::Other, format!("command '{}' {}", argv.connect(" "), reason))
+}
+
+#[cfg(test)]
+mod tests;
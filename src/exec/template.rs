@@ -0,0 +1,77 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::io::{Error, ErrorKind};
+
+const VARS: [&'static str; 5] = ["addr", "prefix", "cidr", "table", "duration"];
+
+// A command template is split into arguments when parsed, before any
+// substitution, so values can never introduce extra arguments and no
+// shell is involved.
+#[derive(Clone, Debug)]
+pub struct Template {
+    argv: Vec<String>,
+}
+
+impl Template {
+    pub fn parse(s: &str) -> Result<Template, Error> {
+        let argv = try!(split(s));
+        if argv.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "empty command"));
+        }
+
+        for arg in &argv {
+            let mut rest = &arg[..];
+            while let Some(start) = rest.find('{') {
+                let end = match rest[start..].find('}') {
+                    Some(end) => start + end,
+                    None      => return Err(Error::new(ErrorKind::InvalidInput, "unterminated placeholder")),
+                };
+                let name = &rest[start + 1..end];
+                if !VARS.iter().any(|var| *var == name) {
+                    return Err(Error::new(ErrorKind::InvalidInput, "unknown placeholder"));
+                }
+                rest = &rest[end + 1..];
+            }
+        }
+
+        Ok(Template { argv: argv })
+    }
+
+    pub fn render(&self, vars: &[(&str, String)]) -> Vec<String> {
+        self.argv.iter().map(|arg| {
+            vars.iter().fold(arg.clone(), |arg, &(name, ref value)| {
+                arg.replace(&format!("{{{}}}", name), value)
+            })
+        }).collect()
+    }
+}
+
+fn split(s: &str) -> Result<Vec<String>, Error> {
+    let mut argv   = Vec::new();
+    let mut arg    = String::new();
+    let mut quoted = false;
+    let mut empty  = true;
+
+    for c in s.chars() {
+        match c {
+            '"'                               => { quoted = !quoted; empty = false; },
+            c if c.is_whitespace() && !quoted => {
+                if !empty {
+                    argv.push(arg);
+                    arg = String::new();
+                    empty = true;
+                }
+            },
+            c                                 => { arg.push(c); empty = false; },
+        }
+    }
+
+    if quoted {
+        return Err(Error::new(ErrorKind::InvalidInput, "unterminated quote"));
+    }
+    if !empty {
+        argv.push(arg);
+    }
+
+    Ok(argv)
+}
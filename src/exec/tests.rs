@@ -0,0 +1,138 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use chrono::Duration;
+use tempdir::TempDir;
+
+use block::Blocker;
+use net::Cidr;
+use super::*;
+
+#[test]
+fn template_render() {
+    let template = Template::parse(r#"/sbin/block "{addr} x" --net={cidr} {duration}"#).unwrap();
+    let argv = template.render(&[
+        ("addr",     "192.0.2.1".to_string()),
+        ("cidr",     "192.0.2.1/32".to_string()),
+        ("duration", "600".to_string()),
+    ]);
+    assert_eq!(vec!["/sbin/block", "192.0.2.1 x", "--net=192.0.2.1/32", "600"], argv);
+}
+
+#[test]
+fn template_no_injection() {
+    let template = Template::parse("/sbin/block {addr}").unwrap();
+    let argv = template.render(&[("addr", "1.2.3.4; rm -rf /".to_string())]);
+    assert_eq!(vec!["/sbin/block", "1.2.3.4; rm -rf /"], argv);
+}
+
+#[test]
+fn template_invalid() {
+    assert!(Template::parse("").is_err());
+    assert!(Template::parse("block {address}").is_err());
+    assert!(Template::parse("block {addr").is_err());
+    assert!(Template::parse("block \"{addr}").is_err());
+}
+
+#[test]
+fn exec_ban_unban() {
+    let dir = TempDir::new("test").unwrap();
+    let log = dir.path().join("log");
+    let ban = script(dir.path(), "ban.sh", &format!("echo ban $@ >> {}", log.display()));
+    let unban = script(dir.path(), "unban.sh", &format!("echo unban $@ >> {}", log.display()));
+
+    let mut exec = Exec::new(Config {
+        unban: Some(format!("/bin/sh {} {{cidr}}", unban)),
+        ..config(format!("/bin/sh {} {{addr}} {{prefix}} {{duration}} {{table}}", ban))
+    }).unwrap();
+
+    let addrs = [cidr("192.0.2.1"), "2001:db8::/48".parse().unwrap()];
+    assert_eq!(2, exec.add("irongate", &addrs, Some(Duration::minutes(10))).unwrap());
+    assert_eq!(1, exec.remove("irongate", &addrs[..1]).unwrap());
+
+    let mut lines: Vec<String> = read(&log).lines().map(|s| s.to_string()).collect();
+    lines.sort();
+    assert_eq!(vec![
+        "ban 192.0.2.1 32 600 irongate",
+        "ban 2001:db8:: 48 600 irongate",
+        "unban 192.0.2.1/32",
+    ], lines);
+}
+
+#[test]
+fn exec_retry() {
+    let dir = TempDir::new("test").unwrap();
+    let count = dir.path().join("count");
+    let ban = script(dir.path(), "ban.sh", &format!("echo x >> {0}; test $(wc -l < {0}) -ge 3", count.display()));
+
+    let mut exec = Exec::new(config(format!("/bin/sh {}", ban))).unwrap();
+    assert_eq!(1, exec.add("irongate", &[cidr("192.0.2.1")], None).unwrap());
+    assert_eq!(3, read(&count).lines().count());
+
+    let mut exec = Exec::new(Config { retries: 0, ..config(format!("/bin/sh {}", ban)) }).unwrap();
+    File::create(&count).unwrap();
+    assert!(exec.add("irongate", &[cidr("192.0.2.1")], None).is_err());
+}
+
+#[test]
+fn exec_unchanged() {
+    let mut exec = Exec::new(Config {
+        unchanged: Some(3),
+        ..config("/bin/sh -c \"exit 3\"".to_string())
+    }).unwrap();
+    assert_eq!(0, exec.add("irongate", &[cidr("192.0.2.1")], None).unwrap());
+}
+
+#[test]
+fn exec_timeout() {
+    assert_eq!(Status::Timeout, run(&argv(&["/bin/sleep", "10"]), 100, false).unwrap().0);
+    assert_eq!(Status::Exit(0), run(&argv(&["/bin/sh", "-c", "exit 0"]), 1000, false).unwrap().0);
+}
+
+#[test]
+fn exec_list() {
+    let dir = TempDir::new("test").unwrap();
+    let list = script(dir.path(), "list.sh", "echo 192.0.2.1; echo 2001:db8::/48; echo garbage; echo $1");
+
+    let mut exec = Exec::new(Config {
+        list: Some(format!("/bin/sh {} {{table}}", list)),
+        ..config("/bin/true".to_string())
+    }).unwrap();
+
+    assert_eq!(vec![cidr("192.0.2.1"), "2001:db8::/48".parse().unwrap()], exec.list("irongate").unwrap());
+}
+
+fn config(ban: String) -> Config {
+    Config {
+        ban:       ban,
+        unban:     None,
+        list:      None,
+        timeout:   5000,
+        retries:   2,
+        backoff:   10,
+        jobs:      2,
+        unchanged: None,
+    }
+}
+
+fn script(dir: &Path, name: &str, body: &str) -> String {
+    let path = dir.join(name);
+    writeln!(&mut File::create(&path).unwrap(), "{}", body).unwrap();
+    path.display().to_string()
+}
+
+fn read(path: &Path) -> String {
+    let mut s = String::new();
+    File::open(path).unwrap().read_to_string(&mut s).unwrap();
+    s
+}
+
+fn argv(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+fn cidr(s: &str) -> Cidr {
+    Cidr::host(s.parse().unwrap())
+}
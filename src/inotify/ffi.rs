@@ -0,0 +1,33 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use libc::types::common::c99::uint32_t;
+use libc::types::os::arch::c95::{c_char, c_int, c_short, c_ulong};
+
+#[repr(C)]
+pub struct pollfd {
+    pub fd:      c_int,
+    pub events:  c_short,
+    pub revents: c_short,
+}
+
+pub const POLLIN: c_short = 0x0001;
+
+pub const IN_CLOEXEC: c_int = 0o2000000;
+
+bitflags! {
+    flags Mask: uint32_t {
+        const IN_MODIFY      = 0x00000002,
+        const IN_MOVED_TO    = 0x00000080,
+        const IN_CREATE      = 0x00000100,
+        const IN_DELETE_SELF = 0x00000400,
+        const IN_MOVE_SELF   = 0x00000800,
+        const IN_IGNORED     = 0x00008000,
+    }
+}
+
+extern {
+    pub fn inotify_init1(flags: c_int) -> c_int;
+    pub fn inotify_add_watch(fd: c_int, path: *const c_char, mask: uint32_t) -> c_int;
+    pub fn inotify_rm_watch(fd: c_int, wd: c_int) -> c_int;
+    pub fn poll(fds: *mut pollfd, nfds: c_ulong, timeout: c_int) -> c_int;
+}
@@ -0,0 +1,125 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+mod ffi;
+
+use libc::funcs::posix88::unistd::{close, read};
+use libc::types::common::c95::c_void;
+use libc::types::os::arch::c95::{c_int, size_t};
+
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind};
+use std::mem;
+use std::time::Duration;
+
+pub use self::ffi::*;
+
+// Size of struct inotify_event without the trailing name.
+const EVENT_LEN: usize = 16;
+
+pub struct Inotify {
+    fd: c_int,
+}
+
+pub struct Event<'a> {
+    pub wd:   c_int,
+    pub mask: Mask,
+    pub name: &'a [u8],
+}
+
+impl Inotify {
+    pub fn new() -> Result<Inotify, Error> {
+        match unsafe { inotify_init1(IN_CLOEXEC) } {
+            -1 => Err(Error::last_os_error()),
+            fd => Ok(Inotify { fd: fd })
+        }
+    }
+
+    pub fn add_watch(&mut self, path: &OsStr, mask: Mask) -> Result<c_int, Error> {
+        let path = path.to_cstring().unwrap();
+        match unsafe { inotify_add_watch(self.fd, path.as_ptr(), mask.bits()) } {
+            -1 => Err(Error::last_os_error()),
+            wd => Ok(wd)
+        }
+    }
+
+    // Watches are removed by the kernel when the file is deleted, so a
+    // watch that no longer exists isn't an error.
+    pub fn rm_watch(&mut self, wd: c_int) {
+        unsafe {
+            inotify_rm_watch(self.fd, wd);
+        }
+    }
+
+    // Wait for events and read them into `buf`, returning the number of
+    // bytes read or 0 if the timeout expired first.
+    pub fn wait(&self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize, Error> {
+        let timeout = match timeout {
+            Some(d) => (d.secs() * 1000 + d.extra_nanos() as u64 / 1000000) as c_int,
+            None    => -1,
+        };
+
+        let mut fds = pollfd { fd: self.fd, events: POLLIN, revents: 0 };
+        match unsafe { poll(&mut fds, 1, timeout) } {
+            -1 => return Err(Error::last_os_error()),
+             0 => return Ok(0),
+             _ => (),
+        }
+
+        match unsafe { read(self.fd, buf.as_mut_ptr() as *mut c_void, buf.len() as size_t) } {
+            -1 => Err(Error::last_os_error()),
+             n => Ok(n as usize),
+        }
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.fd);
+        }
+    }
+}
+
+// Parse the events read by wait, with names stripped of their padding.
+pub fn events(buf: &[u8]) -> Result<Vec<Event>, Error> {
+    let mut events = Vec::new();
+    let mut pos = 0;
+
+    while pos < buf.len() {
+        if buf.len() - pos < EVENT_LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "truncated inotify event"));
+        }
+
+        let wd   = u32_at(buf, pos) as c_int;
+        let mask = u32_at(buf, pos + 4);
+        let len  = u32_at(buf, pos + 12) as usize;
+
+        let end = pos + EVENT_LEN + len;
+        if end > buf.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "truncated inotify event"));
+        }
+
+        let name = &buf[pos + EVENT_LEN..end];
+        let name = match name.position_elem(&0) {
+            Some(n) => &name[..n],
+            None    => name,
+        };
+
+        events.push(Event {
+            wd:   wd,
+            mask: Mask::from_bits_truncate(mask),
+            name: name,
+        });
+        pos = end;
+    }
+
+    Ok(events)
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    let bytes = [buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]];
+    unsafe { mem::transmute(bytes) }
+}
+
+#[cfg(test)]
+mod tests;
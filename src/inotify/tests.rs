@@ -0,0 +1,20 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use super::*;
+
+#[test]
+#[cfg(target_endian = "little")]
+fn parse_events() {
+    let buf = [
+        1, 0, 0, 0,  0, 1, 0, 0,  0, 0, 0, 0,  8, 0, 0, 0,  b'a', b'u', b't', b'h', 0, 0, 0, 0,
+        2, 0, 0, 0,  2, 0, 0, 0,  0, 0, 0, 0,  0, 0, 0, 0,
+    ];
+
+    let parsed: Vec<_> = events(&buf).unwrap().iter().map(|e| (e.wd, e.mask, e.name)).collect();
+    assert_eq!(2, parsed.len());
+    assert!(parsed[0] == (1, IN_CREATE, &b"auth"[..]));
+    assert!(parsed[1] == (2, IN_MODIFY, &b""[..]));
+
+    assert!(events(&buf[..20]).is_err());
+    assert!(events(&buf[..30]).is_err());
+}
@@ -0,0 +1,130 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+mod msg;
+
+use std::io::{Error, ErrorKind};
+use chrono::Duration;
+
+use block::Blocker;
+use net::Cidr;
+use netlink::{Socket, NETLINK_NETFILTER};
+use self::msg::*;
+
+// requests sent at once by add_each, bounding the acks queued for us
+const BATCH: usize = 256;
+
+pub struct Ipset {
+    sock:     Socket,
+    typename: String,
+}
+
+impl Ipset {
+    pub fn new(typename: &str) -> Result<Ipset, Error> {
+        match typename {
+            "hash:ip" | "hash:net" => (),
+            _                      => return Err(Error::new(ErrorKind::InvalidInput, "unsupported ipset type")),
+        }
+
+        Ok(Ipset {
+            sock:     try!(Socket::open(NETLINK_NETFILTER)),
+            typename: typename.to_string(),
+        })
+    }
+
+    fn request(&mut self, buf: &[u8], seq: u32) -> Result<bool, Error> {
+        match self.sock.execute(buf, seq, 1) {
+            Ok(..)                                                  => Ok(true),
+            Err(ref e) if e.raw_os_error() == Some(IPSET_ERR_EXIST) => Ok(false),
+            Err(e)                                                  => Err(e),
+        }
+    }
+}
+
+impl Blocker for Ipset {
+    fn ensure_table(&mut self, table: &str) -> Result<(), Error> {
+        for &(ref set, family) in &sets(table) {
+            let seq = self.sock.next_seq();
+            let buf = create(seq, set, &self.typename, family);
+            try!(self.sock.execute(&buf, seq, 1));
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, table: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<usize, Error> {
+        let timeout = timeout.map(|d| d.num_seconds() as u32);
+        let mut n = 0;
+        for cidr in addrs {
+            let seq = self.sock.next_seq();
+            let buf = add(seq, &set(table, cidr), *cidr, timeout);
+            if try!(self.request(&buf, seq)) {
+                n += 1;
+            }
+        }
+        Ok(n)
+    }
+
+    // ipset handles each request in a buffer on its own, so one send per
+    // batch still gets an ack for every address.
+    fn add_each(&mut self, table: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<Vec<bool>, Error> {
+        let timeout = timeout.map(|d| d.num_seconds() as u32);
+        let mut added = Vec::with_capacity(addrs.len());
+        for chunk in addrs.chunks(BATCH) {
+            let mut buf = Vec::new();
+            let mut first = 0;
+            for (n, cidr) in chunk.iter().enumerate() {
+                let seq = self.sock.next_seq();
+                if n == 0 {
+                    first = seq;
+                }
+                buf.push_all(&add(seq, &set(table, cidr), *cidr, timeout));
+            }
+            for code in try!(self.sock.execute_each(&buf, first, chunk.len())) {
+                match code {
+                    0               => added.push(true),
+                    IPSET_ERR_EXIST => added.push(false),
+                    code            => return Err(Error::from_raw_os_error(code)),
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    fn remove(&mut self, table: &str, addrs: &[Cidr]) -> Result<usize, Error> {
+        let mut n = 0;
+        for cidr in addrs {
+            let seq = self.sock.next_seq();
+            let buf = del(seq, &set(table, cidr), *cidr);
+            if try!(self.request(&buf, seq)) {
+                n += 1;
+            }
+        }
+        Ok(n)
+    }
+
+    fn list(&mut self, table: &str) -> Result<Vec<Cidr>, Error> {
+        let mut cidrs = Vec::new();
+        for &(ref set, _) in &sets(table) {
+            let seq = self.sock.next_seq();
+            for reply in try!(self.sock.execute(&list(seq, set), seq, 0)) {
+                cidrs.extend(try!(parse_list(&reply)).into_iter());
+            }
+        }
+        Ok(cidrs)
+    }
+}
+
+// ipset sets hold a single address family, so each irongate table maps
+// to a pair of sets named <table>_v4 and <table>_v6.
+fn sets(table: &str) -> [(String, u8); 2] {
+    [(format!("{}_v4", table), NFPROTO_IPV4), (format!("{}_v6", table), NFPROTO_IPV6)]
+}
+
+fn set(table: &str, cidr: &Cidr) -> String {
+    match family(cidr.addr()) {
+        NFPROTO_IPV4 => format!("{}_v4", table),
+        _            => format!("{}_v6", table),
+    }
+}
+
+#[cfg(test)]
+mod tests;
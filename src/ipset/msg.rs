@@ -0,0 +1,130 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::io::Error;
+use std::net::IpAddr;
+use net::{self, Cidr};
+use netlink::*;
+
+pub const IPSET_PROTOCOL: u8 = 6;
+
+pub const IPSET_CMD_CREATE: u16 = 2;
+pub const IPSET_CMD_LIST:   u16 = 7;
+pub const IPSET_CMD_ADD:    u16 = 9;
+pub const IPSET_CMD_DEL:    u16 = 10;
+
+const IPSET_ATTR_PROTOCOL: u16 = 1;
+const IPSET_ATTR_SETNAME:  u16 = 2;
+const IPSET_ATTR_TYPENAME: u16 = 3;
+const IPSET_ATTR_REVISION: u16 = 4;
+const IPSET_ATTR_FAMILY:   u16 = 5;
+const IPSET_ATTR_DATA:     u16 = 7;
+const IPSET_ATTR_ADT:      u16 = 8;
+
+const IPSET_ATTR_IP:      u16 = 1;
+const IPSET_ATTR_CIDR:    u16 = 3;
+const IPSET_ATTR_TIMEOUT: u16 = 6;
+
+const IPSET_ATTR_IPADDR_IPV4: u16 = 1;
+const IPSET_ATTR_IPADDR_IPV6: u16 = 2;
+
+pub const IPSET_ERR_EXIST: i32 = 4103;
+
+pub const NFPROTO_IPV4: u8 = 2;
+pub const NFPROTO_IPV6: u8 = 10;
+
+pub fn family(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(..) => NFPROTO_IPV4,
+        IpAddr::V6(..) => NFPROTO_IPV6,
+    }
+}
+
+// Sets are created with a default timeout of 0 so that entries are
+// permanent unless added with their own timeout.
+pub fn create(seq: u32, set: &str, typename: &str, family: u8) -> Vec<u8> {
+    let flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE;
+    let mut msg = Msg::nfgen(kind(IPSET_CMD_CREATE), flags, seq, family, 0);
+    msg.put_u8(IPSET_ATTR_PROTOCOL, IPSET_PROTOCOL);
+    msg.put_str(IPSET_ATTR_SETNAME, set);
+    msg.put_str(IPSET_ATTR_TYPENAME, typename);
+    msg.put_u8(IPSET_ATTR_REVISION, 0);
+    msg.put_u8(IPSET_ATTR_FAMILY, family);
+    msg.begin(IPSET_ATTR_DATA);
+    msg.put_be32(IPSET_ATTR_TIMEOUT | NLA_F_NET_BYTEORDER, 0);
+    msg.end();
+    msg.finish()
+}
+
+pub fn add(seq: u32, set: &str, cidr: Cidr, timeout: Option<u32>) -> Vec<u8> {
+    let flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL;
+    adt(IPSET_CMD_ADD, flags, seq, set, cidr, timeout)
+}
+
+pub fn del(seq: u32, set: &str, cidr: Cidr) -> Vec<u8> {
+    let flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL;
+    adt(IPSET_CMD_DEL, flags, seq, set, cidr, None)
+}
+
+pub fn list(seq: u32, set: &str) -> Vec<u8> {
+    let flags = NLM_F_REQUEST | NLM_F_DUMP;
+    let mut msg = Msg::nfgen(kind(IPSET_CMD_LIST), flags, seq, 0, 0);
+    msg.put_u8(IPSET_ATTR_PROTOCOL, IPSET_PROTOCOL);
+    msg.put_str(IPSET_ATTR_SETNAME, set);
+    msg.finish()
+}
+
+fn adt(cmd: u16, flags: u16, seq: u32, set: &str, cidr: Cidr, timeout: Option<u32>) -> Vec<u8> {
+    let addr = cidr.addr();
+    let mut msg = Msg::nfgen(kind(cmd), flags, seq, family(addr), 0);
+    msg.put_u8(IPSET_ATTR_PROTOCOL, IPSET_PROTOCOL);
+    msg.put_str(IPSET_ATTR_SETNAME, set);
+    msg.begin(IPSET_ATTR_DATA);
+    msg.begin(IPSET_ATTR_IP);
+    msg.put(ipaddr(addr) | NLA_F_NET_BYTEORDER, &net::octets(addr));
+    msg.end();
+    if !cidr.is_host() {
+        msg.put_u8(IPSET_ATTR_CIDR, cidr.len());
+    }
+    if let Some(secs) = timeout {
+        msg.put_be32(IPSET_ATTR_TIMEOUT | NLA_F_NET_BYTEORDER, secs);
+    }
+    msg.end();
+    msg.finish()
+}
+
+pub fn parse_list(payload: &[u8]) -> Result<Vec<Cidr>, Error> {
+    let mut cidrs = Vec::new();
+    let adt = match attr(try!(nfgen_attrs(payload)), IPSET_ATTR_ADT) {
+        Some(adt) => adt,
+        None      => return Ok(cidrs),
+    };
+
+    for (kind, data) in attrs(adt) {
+        if kind != IPSET_ATTR_DATA {
+            continue;
+        }
+        let addr = attr(data, IPSET_ATTR_IP).and_then(|ip| {
+            attrs(ip).into_iter().next().and_then(|(_, bytes)| net::from_octets(bytes))
+        });
+        if let Some(addr) = addr {
+            let len = match attr(data, IPSET_ATTR_CIDR) {
+                Some(len) if len.len() > 0 => len[0],
+                _                          => net::max_len(addr),
+            };
+            cidrs.push(Cidr::new(addr, len));
+        }
+    }
+
+    Ok(cidrs)
+}
+
+fn ipaddr(addr: IpAddr) -> u16 {
+    match addr {
+        IpAddr::V4(..) => IPSET_ATTR_IPADDR_IPV4,
+        IpAddr::V6(..) => IPSET_ATTR_IPADDR_IPV6,
+    }
+}
+
+fn kind(cmd: u16) -> u16 {
+    NFNL_SUBSYS_IPSET << 8 | cmd
+}
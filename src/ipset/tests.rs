@@ -0,0 +1,56 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use net::Cidr;
+use netlink::{Msg, NFNL_SUBSYS_IPSET, NLM_F_MULTI, NLA_F_NET_BYTEORDER};
+use super::msg::*;
+
+#[test]
+#[cfg(target_endian = "little")]
+fn encode_add() {
+    let cidr: Cidr = "203.0.113.0/24".parse().unwrap();
+    assert_eq!(vec![
+        0x4c, 0x00, 0x00, 0x00, 0x09, 0x06, 0x05, 0x02, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x02, 0x00, 0x00, 0x00, 0x05, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00, 0x00, 0x10, 0x00, 0x02, 0x00,
+        0x69, 0x72, 0x6f, 0x6e, 0x67, 0x61, 0x74, 0x65, 0x5f, 0x76, 0x34, 0x00, 0x20, 0x00, 0x07, 0x80,
+        0x0c, 0x00, 0x01, 0x80, 0x08, 0x00, 0x01, 0x40, 0xcb, 0x00, 0x71, 0x00, 0x05, 0x00, 0x03, 0x00,
+        0x18, 0x00, 0x00, 0x00, 0x08, 0x00, 0x06, 0x40, 0x00, 0x00, 0x02, 0x58,
+    ], add(5, "irongate_v4", cidr, Some(600)));
+}
+
+#[test]
+#[cfg(target_endian = "little")]
+fn encode_create() {
+    assert_eq!(vec![
+        0x58, 0x00, 0x00, 0x00, 0x02, 0x06, 0x05, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x05, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00, 0x00, 0x10, 0x00, 0x02, 0x00,
+        0x69, 0x72, 0x6f, 0x6e, 0x67, 0x61, 0x74, 0x65, 0x5f, 0x76, 0x36, 0x00, 0x0d, 0x00, 0x03, 0x00,
+        0x68, 0x61, 0x73, 0x68, 0x3a, 0x6e, 0x65, 0x74, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x04, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x05, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x07, 0x80,
+        0x08, 0x00, 0x06, 0x40, 0x00, 0x00, 0x00, 0x00,
+    ], create(1, "irongate_v6", "hash:net", NFPROTO_IPV6));
+}
+
+#[test]
+fn host_has_no_cidr() {
+    let host: Cidr = "198.51.100.7".parse().unwrap();
+    let net:  Cidr = "198.51.100.0/24".parse().unwrap();
+    assert_eq!(del(1, "s", host).len() + 8, del(1, "s", net).len());
+}
+
+#[test]
+fn parse_list_reply() {
+    let kind = NFNL_SUBSYS_IPSET << 8 | IPSET_CMD_LIST;
+    let mut msg = Msg::nfgen(kind, NLM_F_MULTI, 1, NFPROTO_IPV6, 0);
+    msg.put_str(2, "irongate_v6");
+    msg.begin(8);
+    msg.begin(7).begin(1).put(2 | NLA_F_NET_BYTEORDER, &[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).end();
+    msg.put_u8(3, 48).end();
+    msg.begin(7).begin(1).put(2 | NLA_F_NET_BYTEORDER, &[0x24, 0x04, 0x68, 0, 0x40, 0x04, 0x08, 0x14, 0, 0, 0, 0, 0, 0, 0x20, 0x0e]).end();
+    msg.end();
+    msg.end();
+    let msg = msg.finish();
+
+    let cidrs: Vec<Cidr> = vec!["2001:db8::/48".parse().unwrap(), "2404:6800:4004:814::200e".parse().unwrap()];
+    assert_eq!(cidrs, parse_list(&msg[16..]).unwrap());
+    assert!(parse_list(&msg[16..18]).is_err());
+}
@@ -0,0 +1,80 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::io::Error;
+use std::net::IpAddr;
+use std::ptr;
+
+use libc::consts::os::bsd44::{AF_INET, AF_INET6};
+use libc::types::common::c95::c_void;
+use libc::types::os::arch::c95::{c_char, c_int, c_uint};
+use net;
+
+#[repr(C)]
+pub struct ifaddrs {
+    pub ifa_next:    *mut ifaddrs,
+    pub ifa_name:    *mut c_char,
+    pub ifa_flags:   c_uint,
+    pub ifa_addr:    *mut u8,
+    pub ifa_netmask: *mut u8,
+    pub ifa_dstaddr: *mut u8,
+    pub ifa_data:    *mut c_void,
+}
+
+extern {
+    fn getifaddrs(ifap: *mut *mut ifaddrs) -> c_int;
+    fn freeifaddrs(ifa: *mut ifaddrs);
+}
+
+pub fn interface_addrs() -> Result<Vec<IpAddr>, Error> {
+    let mut addrs = Vec::new();
+    unsafe {
+        let mut head = ptr::null_mut();
+        if getifaddrs(&mut head) == -1 {
+            return Err(Error::last_os_error());
+        }
+
+        let mut ifa = head;
+        while !ifa.is_null() {
+            if let Some(addr) = sockaddr((*ifa).ifa_addr) {
+                addrs.push(addr);
+            }
+            ifa = (*ifa).ifa_next;
+        }
+
+        freeifaddrs(head);
+    }
+    Ok(addrs)
+}
+
+// sockaddr_in holds the address at offset 4 and sockaddr_in6 at offset 8
+// on every supported OS, only the family field differs: a u16 on Linux, a
+// length byte followed by a u8 family on the BSDs.
+unsafe fn sockaddr(sa: *const u8) -> Option<IpAddr> {
+    if sa.is_null() {
+        return None;
+    }
+
+    let bytes = |offset: isize, len: usize| {
+        let mut b = Vec::with_capacity(len);
+        for n in 0..len as isize {
+            b.push(*sa.offset(offset + n));
+        }
+        b
+    };
+
+    match family(sa) {
+        AF_INET  => net::from_octets(&bytes(4, 4)),
+        AF_INET6 => net::from_octets(&bytes(8, 16)),
+        _        => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn family(sa: *const u8) -> c_int {
+    *(sa as *const u16) as c_int
+}
+
+#[cfg(not(target_os = "linux"))]
+unsafe fn family(sa: *const u8) -> c_int {
+    *sa.offset(1) as c_int
+}
@@ -0,0 +1,204 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+mod ffi;
+
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{Error, Read};
+use std::net::{IpAddr, Ipv4Addr};
+use regex::Regex;
+
+use net::{self, Cidr};
+
+// sessions whose close was never logged are dropped oldest first
+const MAX_SESSIONS: usize = 1024;
+
+// Addresses irongate must never block regardless of what the logs say:
+// our own interfaces, the default gateways and the sources of admin
+// sessions that are currently logged in.
+#[derive(Clone)]
+pub struct Lockout {
+    local:    HashSet<IpAddr>,
+    gateways: HashSet<IpAddr>,
+    sessions: Vec<(u32, IpAddr)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reason {
+    Local(IpAddr),
+    Gateway(IpAddr),
+    Session(IpAddr),
+}
+
+impl Lockout {
+    pub fn new() -> Lockout {
+        Lockout {
+            local:    HashSet::new(),
+            gateways: HashSet::new(),
+            sessions: Vec::new(),
+        }
+    }
+
+    pub fn detect() -> Result<Lockout, Error> {
+        let mut lockout = Lockout::new();
+        for addr in try!(ffi::interface_addrs()) {
+            lockout.local.insert(addr);
+        }
+        for addr in try!(gateways()) {
+            lockout.gateways.insert(addr);
+        }
+        Ok(lockout)
+    }
+
+    pub fn add_local(&mut self, addr: IpAddr) {
+        self.local.insert(addr);
+    }
+
+    pub fn add_gateway(&mut self, addr: IpAddr) {
+        self.gateways.insert(addr);
+    }
+
+    // Track admin sessions from sshd's "Accepted" and session close lines.
+    pub fn line(&mut self, line: &str) {
+        if let Some(cap) = ACCEPTED.captures(line) {
+            let pid  = cap.name("pid").and_then(|pid| pid.parse().ok());
+            let addr = cap.name("addr").and_then(|addr| addr.parse().ok());
+            if let (Some(pid), Some(addr)) = (pid, addr) {
+                self.close(pid);
+                if self.sessions.len() >= MAX_SESSIONS {
+                    self.sessions.remove(0);
+                }
+                self.sessions.push((pid, addr));
+            }
+        } else if let Some(cap) = CLOSED.captures(line) {
+            if let Some(pid) = cap.name("pid").and_then(|pid| pid.parse().ok()) {
+                self.close(pid);
+            }
+        }
+    }
+
+    fn close(&mut self, pid: u32) {
+        if let Some(n) = self.sessions.iter().position(|&(p, _)| p == pid) {
+            self.sessions.remove(n);
+        }
+    }
+
+    pub fn check(&self, cidr: Cidr) -> Option<Reason> {
+        let local    = self.local.iter().find(|addr| cidr.contains(**addr));
+        let gateway  = self.gateways.iter().find(|addr| cidr.contains(**addr));
+        let session  = self.sessions.iter().map(|&(_, ref addr)| addr).find(|addr| cidr.contains(**addr));
+        match (local, gateway, session) {
+            (Some(addr), _, _) => Some(Reason::Local(*addr)),
+            (_, Some(addr), _) => Some(Reason::Gateway(*addr)),
+            (_, _, Some(addr)) => Some(Reason::Session(*addr)),
+            _                  => None,
+        }
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Reason::Local(addr)   => write!(fmt, "local interface address {}", addr),
+            Reason::Gateway(addr) => write!(fmt, "default gateway {}", addr),
+            Reason::Session(addr) => write!(fmt, "active session from {}", addr),
+        }
+    }
+}
+
+// Anchored to the whole syslog line so text an attacker controls, like
+// a username in a failed login, can't pose as a session.
+static ACCEPTED: Regex = regex!(r"^\w{3} [ \d]\d \d\d:\d\d:\d\d \S+ sshd\[(?P<pid>\d+)\]: Accepted \S+ for \S+ from (?P<addr>\S+) port \d+ ssh2(: .+)?$");
+static CLOSED:   Regex = regex!(r"^\w{3} [ \d]\d \d\d:\d\d:\d\d \S+ sshd\[(?P<pid>\d+)\]: (pam_unix\(sshd:session\): session closed for user \S+|Disconnected from user \S+ \S+ port \d+)$");
+
+#[cfg(target_os = "linux")]
+fn gateways() -> Result<Vec<IpAddr>, Error> {
+    let mut addrs = route(&try!(read("/proc/net/route")));
+    if let Ok(text) = read("/proc/net/ipv6_route") {
+        addrs.extend(ipv6_route(&text));
+    }
+    Ok(addrs)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn gateways() -> Result<Vec<IpAddr>, Error> {
+    use std::process::Command;
+
+    let mut addrs = Vec::new();
+    for args in &[&["-n", "get", "default"][..], &["-n", "get", "-inet6", "default"][..]] {
+        let output = try!(Command::new("/sbin/route").args(args).output());
+        addrs.extend(route_get(&String::from_utf8_lossy(&output.stdout)));
+    }
+    Ok(addrs)
+}
+
+fn read(path: &str) -> Result<String, Error> {
+    let mut text = String::new();
+    try!(try!(File::open(path)).read_to_string(&mut text));
+    Ok(text)
+}
+
+// /proc/net/route lists the destination and gateway as host order hex.
+pub fn route(text: &str) -> Vec<IpAddr> {
+    let mut addrs = Vec::new();
+    for line in text.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            continue;
+        }
+        if let Ok(gw) = u32::from_str_radix(fields[2], 16) {
+            let b = [gw as u8, (gw >> 8) as u8, (gw >> 16) as u8, (gw >> 24) as u8];
+            if gw != 0 {
+                addrs.push(IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3])));
+            }
+        }
+    }
+    addrs
+}
+
+// /proc/net/ipv6_route lists the destination, its prefix length, the source,
+// its prefix length and then the next hop, all as network order hex.
+pub fn ipv6_route(text: &str) -> Vec<IpAddr> {
+    let mut addrs = Vec::new();
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 5 || fields[1] != "00" || fields[0].chars().any(|c| c != '0') {
+            continue;
+        }
+        if let Some(addr) = hex(fields[4]).and_then(|b| net::from_octets(&b)) {
+            if addr != "::".parse::<IpAddr>().unwrap() {
+                addrs.push(addr);
+            }
+        }
+    }
+    addrs
+}
+
+// `route -n get default` prints the next hop as "gateway: <addr>".
+pub fn route_get(text: &str) -> Vec<IpAddr> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.trim().splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some("gateway"), Some(addr)) => addr.trim().splitn(2, '%').next().unwrap().parse().ok(),
+                _                             => None,
+            }
+        })
+        .collect()
+}
+
+fn hex(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut chars = s.chars();
+    while let (Some(hi), Some(lo)) = (chars.next(), chars.next()) {
+        match (hi.to_digit(16), lo.to_digit(16)) {
+            (Some(hi), Some(lo)) => bytes.push((hi << 4 | lo) as u8),
+            _                    => return None,
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,98 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::net::IpAddr;
+use net::Cidr;
+use super::*;
+use super::MAX_SESSIONS;
+
+#[test]
+fn check_local_and_gateway() {
+    let mut lockout = Lockout::new();
+    lockout.add_local(addr("198.51.100.7"));
+    lockout.add_gateway(addr("198.51.100.1"));
+
+    assert_eq!(Some(Reason::Local(addr("198.51.100.7"))), lockout.check(cidr("198.51.100.7")));
+    assert_eq!(Some(Reason::Gateway(addr("198.51.100.1"))), lockout.check(cidr("198.51.100.1")));
+    assert!(lockout.check(cidr("198.51.100.0/24")).is_some());
+    assert_eq!(None, lockout.check(cidr("198.51.100.8")));
+}
+
+#[test]
+fn sessions() {
+    let mut lockout = Lockout::new();
+    lockout.line("Jun  3 10:12:01 host sshd[4211]: Accepted publickey for will from 203.0.113.9 port 52344 ssh2: RSA SHA256:xyz");
+    lockout.line("Jun  3 10:12:07 host sshd[4212]: Accepted keyboard-interactive/pam for root from 2001:db8::9 port 50534 ssh2");
+
+    assert_eq!(Some(Reason::Session(addr("203.0.113.9"))), lockout.check(cidr("203.0.113.9")));
+    assert_eq!(Some(Reason::Session(addr("2001:db8::9"))), lockout.check(cidr("2001:db8::9")));
+
+    lockout.line("Jun  3 11:40:13 host sshd[4211]: pam_unix(sshd:session): session closed for user will");
+    lockout.line("Jun  3 11:40:20 host sshd[4212]: Disconnected from user root 2001:db8::9 port 50534");
+
+    assert_eq!(None, lockout.check(cidr("203.0.113.9")));
+    assert_eq!(None, lockout.check(cidr("2001:db8::9")));
+}
+
+#[test]
+fn injected_session() {
+    let mut lockout = Lockout::new();
+    lockout.line("Jun  3 10:12:01 host sshd[4213]: Invalid user sshd[1]: Accepted password for x from 203.0.113.9 port 1 ssh2 from 192.0.2.7 port 40122");
+    lockout.line("Jun  3 10:12:02 host sshd[4213]: Failed password for invalid user sshd[1]: Accepted password for x from 203.0.113.9 port 1 ssh2 from 192.0.2.7 port 40122 ssh2");
+    lockout.line("Jun  3 10:12:03 host logger: sshd[1]: Accepted password for x from 203.0.113.9 port 1 ssh2");
+
+    assert_eq!(None, lockout.check(cidr("203.0.113.9")));
+    assert_eq!(None, lockout.check(cidr("192.0.2.7")));
+}
+
+#[test]
+fn bounded_sessions() {
+    let mut lockout = Lockout::new();
+    for pid in 0..MAX_SESSIONS + 1 {
+        let n = pid as u32 + 1;
+        lockout.line(&format!("Jun  3 10:12:01 host sshd[{}]: Accepted publickey for will from 10.{}.{}.{} port 52344 ssh2",
+                              pid, n >> 16 & 0xff, n >> 8 & 0xff, n & 0xff));
+    }
+
+    assert_eq!(MAX_SESSIONS, lockout.sessions.len());
+    assert_eq!(None, lockout.check(cidr("10.0.0.1")));
+    assert!(lockout.check(cidr("10.0.0.2")).is_some());
+}
+
+#[test]
+fn proc_route() {
+    let text = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0
+";
+    assert_eq!(vec![addr("192.168.1.1")], route(text));
+}
+
+#[test]
+fn proc_ipv6_route() {
+    let text = "\
+20010db8000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003     eth0
+";
+    assert_eq!(vec![addr("fe80::1")], ipv6_route(text));
+}
+
+#[test]
+fn route_get_default() {
+    let text = "   route to: default
+destination: default
+       mask: default
+    gateway: 192.168.1.1
+  interface: em0
+";
+    assert_eq!(vec![addr("192.168.1.1")], route_get(text));
+    assert_eq!(vec![addr("fe80::1")], route_get("    gateway: fe80::1%em0\n"));
+}
+
+fn cidr(s: &str) -> Cidr {
+    s.parse().unwrap()
+}
+
+fn addr(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
@@ -1,7 +1,15 @@
 // Copyright (C) 2015 - Will Glozer.  All rights reserved.
 
 macro_rules! syslog {
-    ($($arg:tt)*) => {{
+    ($($arg:tt)*) => { syslog_at!(5, $($arg)*) }
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => { syslog_at!(4, $($arg)*) }
+}
+
+macro_rules! syslog_at {
+    ($priority:expr, $($arg:tt)*) => {{
         use std::ffi::CString;
         use libc::types::os::arch::c95::{c_int, c_char};
         
@@ -10,6 +18,6 @@ macro_rules! syslog {
         }
 
         let cstr = CString::new(format!($($arg)*)).unwrap();
-        unsafe { syslog(5, cstr.as_ptr()) };
+        unsafe { syslog($priority, cstr.as_ptr()) };
     }}
 }
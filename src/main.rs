@@ -4,10 +4,29 @@
 #![plugin(docopt_macros, regex_macros)]
 #![allow(dead_code)]
 
+mod allow;
+mod ban;
+mod block;
 mod cms;
+mod deny;
+mod evict;
+mod exec;
+#[cfg(target_os = "linux")]
+mod inotify;
+mod ipset;
+#[cfg(not(target_os = "linux"))]
 mod kqueue;
+mod lockout;
+mod net;
+mod netlink;
+mod nft;
 mod pf;
 mod posix;
+mod privsep;
+mod rules;
+mod sandbox;
+mod snapshot;
+mod store;
 mod tail;
 #[macro_use]
 mod log;
@@ -24,32 +43,171 @@ extern crate chrono;
 extern crate regex;
 extern crate sketchy;
 
+use std::cmp;
+use std::env;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
 use std::net::IpAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process;
 use chrono::*;
 use regex::Regex;
-use cms::TimeWindowCMS;
-use pf::{Pf, Addr, Table};
+use allow::Allowlist;
+use ban::Bans;
+use block::{Blocker, Memory};
+use deny::HostsDeny;
+use evict::{Entry, Eviction, Policy};
+use exec::Exec;
+use ipset::Ipset;
+use lockout::Lockout;
+use net::Cidr;
+use nft::Nft;
+use pf::{Pf, TableFlags};
+use privsep::{Client, Reply, Request, Server};
+use rules::{Counter, Decision, Rules};
+use sandbox::Sandbox;
+use snapshot::Snapshot;
+use store::{State, Store};
 use tail::Tailer;
 
 docopt!(Args derive Debug, "
 Usage: irongate [options] <logfile>
+       irongate [options] --status
+       irongate --sandbox-test
        irongate --help
 
 Options:
-  -l, --limit <limit>    Maximum attempts per period [default: 3].
-  -p, --period <period>  Attempt monitoring period in minutes [default: 1].
-  -t, --table <table>    Add addresses to this table.
-", flag_limit: u64, flag_period: u64);
+  -S, --status                Print table and address statistics and exit.
+  -B, --backend <name>        Firewall backend, pf, nft, ipset, exec or hosts-deny [default: pf].
+  -l, --limit <limit>         Maximum attempts per period [default: 3].
+  -L, --prefix-limit <limit>  Maximum attempts per prefix per period, 0 to disable [default: 0].
+  -p, --period <period>       Attempt monitoring period in minutes [default: 1].
+  -n, --dry-run               Log addresses that would be blocked without blocking them.
+  --shadow-limit <limit>      Evaluate shadow rules with this limit alongside the live
+                              rules and log the addresses they would block.
+  --shadow-prefix-limit <limit>  Shadow rule prefix limit, 0 to disable [default: 0].
+  --shadow-period <period>    Shadow rule monitoring period in minutes [default: 1].
+  -b, --ban-time <minutes>    Remove addresses after this many minutes, 0 for never.
+                              A comma-separated list escalates repeat offenders [default: 0].
+  -a, --allow <list>          Comma-separated addresses and networks never to block.
+  --allow-file <file>         Never block addresses and networks listed in this file.
+  --allow-table <table>       Never block addresses in this existing pf table.
+  -K, --keep-active <n>       Extend bans on addresses that had at least this many
+                              packets blocked during the ban, 0 to disable [default: 0].
+  -d, --decay <minutes>       Forget prior bans after this many minutes [default: 10080].
+  --batch-size <n>            Add at most this many addresses per firewall update [default: 64].
+  --batch-delay <ms>          Wait at most this long to fill a batch [default: 100].
+  -m, --max-entries <n>       Keep at most this many banned entries, 0 for no limit [default: 0].
+  -e, --evict <policy>        How to make room at the limit: oldest, fewest-hits or
+                              collapse into prefixes [default: oldest].
+  -s, --state <file>          Persist bans and offense history to this file.
+  -r, --resync                Replace the table contents with the saved bans on startup.
+  -t, --table <table>         Add addresses to this table.
+  -u, --user <user>           Parse logs as this user in a chrooted process, leaving
+                              firewall updates to a privileged parent process.
+  --chroot <dir>              Directory to confine the log parser to, by default the log
+                              file's directory. State and table files are opened inside it.
+  --sandbox                   On Linux, restrict file access and system calls once
+                              log files and the firewall are open. The exec and
+                              hosts-deny backends also need --user.
+  --sandbox-test              Check that the sandbox rejects a forbidden system call.
+  -A, --anchor <path>         Manage pf tables inside this anchor.
+  --table-flags <list>        Flags for created pf tables: persist, const and counters
+                              [default: persist,counters].
+  -k, --kill-states           Kill established pf states from blocked addresses.
+  -f, --table-file <file>     Also write banned addresses to this pf table file.
+  --table-file-delay <secs>   Seconds without changes before rewriting the table file [default: 5].
+  --prefix4 <len>             IPv4 prefix length for aggregation [default: 24].
+  --prefix6 <len>             IPv6 prefix length for aggregation [default: 64].
+  --nft-family <family>       nftables family: inet, ip or ip6 [default: inet].
+  --nft-table <table>         nftables table holding the <table>_v4 and <table>_v6
+                              address sets [default: irongate].
+  --ipset-type <type>         ipset type for the <table>_v4 and <table>_v6 sets,
+                              hash:ip or hash:net [default: hash:net].
+  --exec-ban <cmd>            Command run to ban an address. {addr}, {prefix}, {cidr},
+                              {table} and {duration} are replaced in each argument.
+  --exec-unban <cmd>          Command run to unban an address.
+  --exec-list <cmd>           Command printing banned addresses, one per line.
+  --exec-timeout <ms>         Kill commands running longer than this [default: 10000].
+  --exec-retries <n>          Retry failed commands this many times [default: 2].
+  --exec-backoff <ms>         Initial delay between retries [default: 500].
+  --exec-jobs <n>             Maximum concurrent commands [default: 4].
+  --exec-unchanged <status>   Exit status reporting an address was already (un)banned.
+  --deny-file <file>          TCP wrappers file to keep banned addresses in [default: /etc/hosts.deny].
+  --deny-daemons <list>       Daemon list for banned address rules [default: sshd].
+", flag_limit: u64, flag_keep_active: u64, flag_batch_size: usize, flag_batch_delay: i64, flag_max_entries: usize, flag_prefix_limit: u64, flag_period: u64, flag_decay: u64, flag_prefix4: u8, flag_prefix6: u8, flag_state: Option<String>,
+  flag_allow: Option<String>, flag_allow_file: Option<String>, flag_allow_table: Option<String>,
+  flag_shadow_limit: Option<u64>, flag_shadow_prefix_limit: u64, flag_shadow_period: u64,
+  flag_anchor: Option<String>, flag_evict: String, flag_user: Option<String>, flag_chroot: Option<String>, flag_table_flags: String, flag_table_file: Option<String>, flag_table_file_delay: i64,
+  flag_exec_ban: Option<String>, flag_exec_unban: Option<String>, flag_exec_list: Option<String>,
+  flag_exec_timeout: u32, flag_exec_retries: u32, flag_exec_backoff: u32, flag_exec_jobs: usize,
+  flag_exec_unchanged: Option<i32>);
 
 fn main() {
     let args: Args = Args::docopt().decode().unwrap_or_else(|e| e.exit());
 
-    let file = args.arg_logfile;
+    if args.flag_status {
+        let backend = backend(&args).unwrap_or_else(|e| e.exit());
+        let store   = args.flag_state.as_ref().map(Store::new);
+        if let Err(e) = status(&backend, &args.flag_table, store.as_ref()) {
+            let _ = writeln!(&mut io::stderr(), "Failed to read table status: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.flag_sandbox_test {
+        if let Err(e) = sandbox::check() {
+            let _ = writeln!(&mut io::stderr(), "Sandbox check failed: {}", e);
+            process::exit(1);
+        }
+        println!("Sandbox rejected a forbidden system call");
+        return;
+    }
+
+    let mut allow = allowlist(args.flag_allow.as_ref().map_or("", |list| &list[..])).unwrap_or_else(|e| e.exit());
+    if let Err(e) = load_allowlist(&mut allow, &args) {
+        println!("Failed to load allowlist: {}", e);
+        return;
+    }
+
+    let file  = args.arg_logfile;
+    let rules = Rules {
+        limit:        args.flag_limit,
+        prefix_limit: args.flag_prefix_limit,
+        prefix4:      args.flag_prefix4,
+        prefix6:      args.flag_prefix6,
+        period:       Duration::minutes(args.flag_period as i64),
+    };
     let gate = IronGate {
-        table:  &args.flag_table,
-        limit:  args.flag_limit,
-        period: Duration::minutes(args.flag_period as i64),
+        table:        &args.flag_table,
+        rules:        rules,
+        shadow:       args.flag_shadow_limit.map(|limit| Rules {
+            limit:        limit,
+            prefix_limit: args.flag_shadow_prefix_limit,
+            period:       Duration::minutes(args.flag_shadow_period as i64),
+            ..rules
+        }),
+        dry_run:      args.flag_dry_run,
+        kill_states:  args.flag_kill_states,
+        resync:       args.flag_resync,
+        keep_active:  args.flag_keep_active,
+        batch_size:   cmp::max(1, args.flag_batch_size),
+        batch_delay:  Duration::milliseconds(args.flag_batch_delay),
+        max_entries:  args.flag_max_entries,
+        evict:        policy(&args.flag_evict).unwrap_or_else(|e| e.exit()),
+        allow:        allow,
+        ban_times:    ban_times(&args.flag_ban_time).unwrap_or_else(|e| e.exit()),
+        decay:        Duration::minutes(args.flag_decay as i64),
+        store:        args.flag_state.as_ref().map(|path| Store::new(inside(&args, path))),
+        table_file:   args.flag_table_file.as_ref().map(|file| {
+            (inside(&args, file), Duration::seconds(args.flag_table_file_delay))
+        }),
+        backend:      backend(&args).unwrap_or_else(|e| e.exit()),
+        privsep:      privsep(&args).unwrap_or_else(|e| e.exit()),
+        sandbox:      sandbox(&args).unwrap_or_else(|e| e.exit()),
     };
 
     match gate.monitor(Path::new(&file)) {
@@ -59,34 +217,691 @@ fn main() {
 }
 
 struct IronGate<'a> {
-    limit:  u64,
-    period: Duration,
-    table:  &'a str,
+    rules:        Rules,
+    shadow:       Option<Rules>,
+    dry_run:      bool,
+    kill_states:  bool,
+    resync:       bool,
+    keep_active:  u64,
+    batch_size:   usize,
+    batch_delay:  Duration,
+    max_entries:  usize,
+    evict:        Policy,
+    allow:        Allowlist,
+    ban_times:    Vec<Option<Duration>>,
+    decay:        Duration,
+    store:        Option<Store>,
+    table_file:   Option<(String, Duration)>,
+    backend:      Backend,
+    privsep:      Option<(String, PathBuf)>,
+    sandbox:      bool,
+    table:        &'a str,
+}
+
+enum Backend {
+    Pf(Option<String>, TableFlags),
+    Nft(String, String),
+    Ipset(String),
+    Exec(exec::Config),
+    HostsDeny(String, String),
 }
 
 impl<'a> IronGate<'a> {
     fn monitor(&self, path: &Path) -> Result<(), tail::Error> {
-        let pf = try!(Pf::new());
-        let mut tailer = try!(Tailer::new(path.as_os_str()));
-        let resolution = |d: &Duration| { d.num_seconds() };
-        let mut cms = TimeWindowCMS::new(self.period, &resolution);
+        if self.dry_run {
+            return self.run(Memory::new(), path);
+        }
+
+        match self.backend {
+            Backend::Pf(ref anchor, flags)            => {
+                let mut pf = try!(Pf::with_anchor(anchor.as_ref().map(|a| &a[..])));
+                pf.set_table_flags(flags);
+                self.start(pf, path)
+            },
+            Backend::Nft(ref family, ref table)       => self.start(try!(Nft::new(family, table)), path),
+            Backend::Ipset(ref kind)                  => self.start(try!(Ipset::new(kind)), path),
+            Backend::Exec(ref config)                 => self.start(try!(Exec::new(config.clone())), path),
+            Backend::HostsDeny(ref file, ref daemons) => self.start(HostsDeny::new(file, daemons), path),
+        }
+    }
 
-        try!(pf.add_tables(&vec![Table::new(self.table)]));
+    fn start<B: Blocker>(&self, blocker: B, path: &Path) -> Result<(), tail::Error> {
+        match self.privsep {
+            Some((ref user, ref root)) => self.separate(blocker, user, root, path),
+            None                       => self.run(blocker, path),
+        }
+    }
+
+    fn run<B: Blocker>(&self, blocker: B, path: &Path) -> Result<(), tail::Error> {
+        let tailer = try!(Tailer::new(path.as_os_str()));
+        self.watch(blocker, tailer, lockout(), path)
+    }
+
+    // Keep the firewall in this process and fork a child that parses logs
+    // chrooted as an unprivileged user, sending it bans over a socketpair.
+    fn separate<B: Blocker>(&self, mut blocker: B, user: &str, root: &Path, path: &Path) -> Result<(), tail::Error> {
+        let path = match privsep::confined(&try!(env::current_dir()).join(path), root) {
+            Some(path) => path,
+            None       => {
+                let e = io::Error::new(io::ErrorKind::InvalidInput, "log file is outside the chroot");
+                return Err(tail::Error::IoError(e));
+            },
+        };
+
+        try!(blocker.ensure_table(self.table));
+        let lockout = lockout();
+        let (parent, child) = try!(privsep::socketpair());
+
+        match try!(privsep::fork()) {
+            0   => {
+                mem::drop(blocker);
+                mem::drop(parent);
+                try!(privsep::confine(user, root));
+                let tailer = try!(Tailer::new(path.as_os_str()));
+                self.watch(Client::new(child), tailer, lockout, &path)
+            },
+            pid => {
+                mem::drop(child);
+                try!(self.serve(&mut blocker, Server::new(parent), &lockout));
+                try!(privsep::wait(pid));
+                Ok(())
+            },
+        }
+    }
+
+    // Limit file access to the log directory and the directories of files
+    // this process rewrites, and system calls to those the main loop makes.
+    fn enter_sandbox(&self, path: &Path) -> Result<(), io::Error> {
+        let mut sandbox = Sandbox::new();
+        sandbox.read(path);
+        if let Some(ref store) = self.store {
+            sandbox.write(store.path());
+        }
+        if let Some((ref file, _)) = self.table_file {
+            sandbox.write(Path::new(file));
+        }
+
+        match try!(sandbox.enable()) {
+            true  => syslog!("Sandbox enabled"),
+            false => warning!("Sandbox enabled without landlock, file access is unrestricted"),
+        }
+        Ok(())
+    }
+
+    // Apply bans and unbans from the child until it exits. A malformed
+    // request ends the session. The child is not trusted, so bans are
+    // checked again against the allowlist, lockout and prefix lengths.
+    fn serve<B: Blocker>(&self, blocker: &mut B, mut server: Server<File>, lockout: &Lockout) -> Result<(), io::Error> {
+        let table = self.table;
+        while let Some(request) = try!(server.next()) {
+            let result = match request {
+                Request::Ban(cidr, _) if !is_global(cidr.addr()) => {
+                    warning!("Refusing to block {}: not a global address", cidr);
+                    Ok(0)
+                },
+                Request::Ban(cidr, _) if self.allow.overlaps(cidr) => {
+                    warning!("Refusing to block {}: overlaps allowlist", cidr);
+                    Ok(0)
+                },
+                Request::Ban(cidr, _) if cidr.len() < self.min_len(cidr.addr()) => {
+                    warning!("Refusing to block {}: prefix is shorter than /{}", cidr, self.min_len(cidr.addr()));
+                    Ok(0)
+                },
+                Request::Ban(cidr, ban) => match lockout.check(cidr) {
+                    Some(reason) => {
+                        warning!("Refusing to block {}: {}", cidr, reason);
+                        Ok(0)
+                    },
+                    None => blocker.add(table, &[cidr], ban),
+                },
+                Request::Unban(cidr) => blocker.remove(table, &[cidr]),
+            };
+
+            let reply = match result {
+                Ok(0)  => Reply::Unchanged,
+                Ok(..) => Reply::Changed,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                    try!(blocker.ensure_table(table));
+                    Reply::Missing
+                },
+                Err(e) => {
+                    warning!("Failed to update table '{}': {}", table, e);
+                    Reply::Failed
+                },
+            };
+            try!(server.reply(reply));
+        }
+        Ok(())
+    }
+
+    fn watch<B: Blocker>(&self, blocker: B, mut tailer: Tailer, lockout: Lockout, path: &Path) -> Result<(), tail::Error> {
+        let mut monitor = try!(Monitor::with_lockout(self, blocker, lockout));
+        if self.sandbox {
+            try!(self.enter_sandbox(path));
+        }
+        let ms   = cmp::max(1, cmp::min(1000, self.batch_delay.num_milliseconds()));
+        let tick = std::time::Duration::new(0, ms as u32 * 1000000);
 
         loop {
-            if let Ok(Some(line)) = tailer.next_line(None) {
-                if let Ok(timestamp) = timestamp(line, Local::now()) {
-                    if let Some(addr) = matches(line) {
-                        if is_global(addr) && cms.add(timestamp, addr) > self.limit {
-                            let addrs = vec![Addr::new(addr)];
-                            if try!(pf.add_addrs(self.table, &addrs)) == 1 {
-                                syslog!("Address added to table '{}': {}", self.table,  addr);
-                            }
-                        }
-                    }
+            if let Ok(Some(line)) = tailer.next_line(Some(tick)) {
+                try!(monitor.line(line));
+            }
+            try!(monitor.tick(UTC::now()));
+            try!(monitor.expire(UTC::now()));
+        }
+    }
+
+    fn min_len(&self, addr: IpAddr) -> u8 {
+        match addr {
+            IpAddr::V4(..) => self.rules.prefix4,
+            IpAddr::V6(..) => self.rules.prefix6,
+        }
+    }
+
+    fn ban_time(&self, level: u32) -> Option<Duration> {
+        let last = self.ban_times.len() - 1;
+        self.ban_times[cmp::min(level as usize, last)]
+    }
+}
+
+struct Monitor<'a, B: Blocker> {
+    gate:     &'a IronGate<'a>,
+    blocker:  B,
+    counter:  Counter,
+    shadow:   Option<Counter>,
+    shadowed: HashMap<Cidr, DateTime<UTC>>,
+    pruned:   DateTime<UTC>,
+    bans:     Bans,
+    lockout:  Lockout,
+    blocked:  HashMap<Cidr, u64>,
+    pending:  Vec<Pending>,
+    queued:   Option<DateTime<UTC>>,
+    evicted:  usize,
+    near:     bool,
+    snapshot: Option<Snapshot>,
+}
+
+struct Pending {
+    decision: Decision,
+    level:    u32,
+    ban:      Option<Duration>,
+}
+
+impl<'a, B: Blocker> Monitor<'a, B> {
+    fn new(gate: &'a IronGate<'a>, blocker: B) -> Result<Monitor<'a, B>, io::Error> {
+        Monitor::with_lockout(gate, blocker, Lockout::new())
+    }
+
+    fn with_lockout(gate: &'a IronGate<'a>, mut blocker: B, lockout: Lockout) -> Result<Monitor<'a, B>, io::Error> {
+        try!(blocker.ensure_table(gate.table));
+
+        let mut monitor = Monitor {
+            gate:     gate,
+            blocker:  blocker,
+            counter:  Counter::new(gate.rules),
+            shadow:   gate.shadow.map(Counter::new),
+            shadowed: HashMap::new(),
+            pruned:   UTC::now(),
+            bans:     Bans::new(gate.decay),
+            lockout:  lockout,
+            blocked:  HashMap::new(),
+            pending:  Vec::new(),
+            queued:   None,
+            evicted:  0,
+            near:     false,
+            snapshot: match gate.dry_run {
+                false => gate.table_file.as_ref().map(|&(ref file, delay)| Snapshot::new(file, delay)),
+                true  => None,
+            },
+        };
+
+        try!(monitor.restore());
+        monitor.changed();
+        Ok(monitor)
+    }
+
+    fn line(&mut self, line: &str) -> Result<(), io::Error> {
+        self.lockout.line(line);
+        if let Some((timestamp, addr)) = parse(line) {
+            if is_global(addr) && !self.gate.allow.contains(addr) {
+                for decision in self.counter.count(timestamp, addr) {
+                    try!(self.block(decision));
+                }
+                self.shadow(timestamp, addr);
+            }
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, decision: Decision) -> Result<(), io::Error> {
+        let cidr = decision.cidr;
+        if self.gate.allow.overlaps(cidr) {
+            syslog!("Not blocking {}: overlaps allowlist", cidr);
+            return Ok(());
+        }
+        if let Some(reason) = self.lockout.check(cidr) {
+            warning!("Refusing to block {}: {}", cidr, reason);
+            return Ok(());
+        }
+        if self.bans.contains(&cidr) || self.pending.iter().any(|p| p.decision.cidr == cidr) {
+            return Ok(());
+        }
+
+        let now   = UTC::now();
+        let level = self.bans.offenses(&cidr, now);
+        self.pending.push(Pending {
+            decision: decision,
+            level:    level,
+            ban:      self.gate.ban_time(level),
+        });
+        self.queued = self.queued.or(Some(now));
+
+        match self.pending.len() >= self.gate.batch_size {
+            true  => self.flush(),
+            false => Ok(()),
+        }
+    }
+
+    fn tick(&mut self, now: DateTime<UTC>) -> Result<(), io::Error> {
+        match self.queued {
+            Some(since) if now - since >= self.gate.batch_delay => self.flush(),
+            _                                                   => Ok(()),
+        }
+    }
+
+    // Send queued decisions to the firewall, one request per ban duration.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        let table   = self.gate.table;
+        let pending = mem::replace(&mut self.pending, Vec::new());
+        self.queued = None;
+
+        try!(self.make_room(&pending));
+
+        let mut durations = Vec::new();
+        for p in &pending {
+            if !durations.contains(&p.ban) {
+                durations.push(p.ban);
+            }
+        }
+
+        let mut changed = false;
+        for ban in durations {
+            let batch: Vec<&Pending> = pending.iter().filter(|p| p.ban == ban).collect();
+            let cidrs: Vec<Cidr>     = batch.iter().map(|p| p.decision.cidr).collect();
+            let added: Vec<Option<bool>> = match self.update(|blocker| blocker.add_each(table, &cidrs, ban)) {
+                Ok(added) => added.into_iter().map(Some).collect(),
+                Err(e)    => {
+                    warning!("Failed to add {} addresses to table '{}': {}", cidrs.len(), table, e);
+                    self.add_singly(&cidrs, ban)
+                },
+            };
+
+            let now = UTC::now();
+            for (p, added) in batch.into_iter().zip(added) {
+                let added = match added {
+                    Some(added) => added,
+                    None        => continue,
+                };
+                // entries left in the table by an earlier run still need
+                // tracking so they expire
+                self.bans.insert(p.decision.cidr, now, ban);
+                changed = true;
+                match added {
+                    true  => self.added(p),
+                    false => syslog!("Address already in table '{}': {} (level {}, {})",
+                                     table, p.decision.cidr, p.level + 1, describe(ban)),
                 }
             }
         }
+
+        if changed {
+            self.changed();
+        }
+        self.capacity();
+        Ok(())
+    }
+
+    // Retry a failed batch one address at a time so a single failure, like
+    // a ban command exiting with an error, only skips that address.
+    fn add_singly(&mut self, cidrs: &[Cidr], ban: Option<Duration>) -> Vec<Option<bool>> {
+        let table = self.gate.table;
+        let mut added = Vec::with_capacity(cidrs.len());
+        for cidr in cidrs {
+            added.push(match self.update(|blocker| blocker.add(table, &[*cidr], ban)) {
+                Ok(n)  => Some(n == 1),
+                Err(e) => {
+                    warning!("Failed to add {} to table '{}': {}", cidr, table, e);
+                    None
+                },
+            });
+        }
+        added
+    }
+
+    fn added(&mut self, p: &Pending) {
+        let table = self.gate.table;
+        let cidr  = p.decision.cidr;
+        if self.gate.dry_run {
+            syslog!("Would block {} (count {}, rule {}, level {}, {})",
+                    cidr, p.decision.count, p.decision.rule, p.level + 1, describe(p.ban));
+        } else if self.gate.kill_states {
+            let killed = match self.blocker.kill_states(&[cidr]) {
+                Ok(n)  => n,
+                Err(e) => {
+                    warning!("Failed to kill states from {}: {}", cidr, e);
+                    0
+                },
+            };
+            syslog!("Address added to table '{}': {} (level {}, {}, {} states killed)",
+                    table, cidr, p.level + 1, describe(p.ban), killed);
+        } else {
+            syslog!("Address added to table '{}': {} (level {}, {})", table, cidr, p.level + 1, describe(p.ban));
+        }
+    }
+
+    // Evict entries so the pending bans not already in the table fit under
+    // the configured maximum.
+    fn make_room(&mut self, pending: &[Pending]) -> Result<(), io::Error> {
+        let max = self.gate.max_entries;
+        let new: HashSet<Cidr> = pending.iter().map(|p| p.decision.cidr).filter(|cidr| {
+            !self.bans.contains(cidr)
+        }).collect();
+        let excess = (self.bans.len() + new.len()).saturating_sub(max);
+        if max == 0 || excess == 0 {
+            return Ok(());
+        }
+
+        let entries = try!(self.evictable());
+        let plan    = {
+            let (allow, lockout) = (&self.gate.allow, &self.lockout);
+            let lens = (self.gate.rules.prefix4, self.gate.rules.prefix6);
+            evict::plan(self.gate.evict, &entries, excess, lens, |prefix| {
+                !allow.overlaps(prefix) && lockout.check(prefix).is_none()
+            })
+        };
+        if plan.is_empty() {
+            warning!("Table '{}' reached {} entries with no bans that can be evicted",
+                     self.gate.table, max);
+            return Ok(());
+        }
+
+        // one summary however many entries go, even if an eviction fails
+        let (mut freed, mut prefixes, mut result) = (0, 0, Ok(()));
+        for eviction in &plan {
+            if let Err(e) = self.evict(eviction) {
+                result = Err(e);
+                break;
+            }
+            freed += eviction.freed();
+            if let Eviction::Collapse(..) = *eviction {
+                prefixes += 1;
+            }
+        }
+
+        self.evicted += freed;
+        warning!("Table '{}' reached {} entries, evicted {} ({} collapsed into prefixes, {} evicted so far)",
+                 self.gate.table, max, freed, prefixes, self.evicted);
+        self.changed();
+        result
+    }
+
+    fn evictable(&mut self) -> Result<Vec<Entry>, io::Error> {
+        let now = UTC::now();
+        // state saved before ban starts were recorded only has the offense
+        let history: HashMap<Cidr, DateTime<UTC>> = self.bans.history().into_iter().map(|(cidr, offense)| {
+            (cidr, offense.last)
+        }).collect();
+        let packets: HashMap<Cidr, u64> = match self.gate.evict {
+            Policy::FewestHits => try!(self.blocker.stats(self.gate.table)).into_iter().map(|stats| {
+                (stats.cidr, stats.packets)
+            }).collect(),
+            _                  => HashMap::new(),
+        };
+
+        Ok(self.bans.entries().into_iter().map(|(cidr, _)| Entry {
+            cidr:  cidr,
+            added: self.bans.started(&cidr).or(history.get(&cidr).cloned()).unwrap_or(now),
+            hits:  packets.get(&cidr).cloned().unwrap_or(0),
+        }).collect())
+    }
+
+    fn evict(&mut self, eviction: &Eviction) -> Result<(), io::Error> {
+        let table = self.gate.table;
+        let now   = UTC::now();
+        let cidrs = match *eviction {
+            Eviction::Remove(cidr)                => vec![cidr],
+            Eviction::Collapse(prefix, ref cidrs) => {
+                // the prefix is banned for as long as its longest member
+                let mut expires = Some(now);
+                for cidr in cidrs {
+                    expires = match (expires, self.bans.expires(cidr).unwrap_or(None)) {
+                        (Some(a), Some(b)) => Some(cmp::max(a, b)),
+                        _                  => None,
+                    };
+                }
+                try!(self.update(|blocker| blocker.add(table, &[prefix], expires.map(|when| when - now))));
+                self.bans.schedule(prefix, expires);
+                self.bans.start(prefix, now);
+                cidrs.clone()
+            },
+        };
+
+        try!(self.update(|blocker| blocker.remove(table, &cidrs)));
+        for cidr in &cidrs {
+            self.bans.remove(cidr);
+            self.blocked.remove(cidr);
+        }
+        Ok(())
+    }
+
+    // Warn once each time the table passes 90% of its maximum size.
+    fn capacity(&mut self) {
+        let max  = self.gate.max_entries;
+        let len  = self.bans.len();
+        let near = max > 0 && len * 10 >= max * 9;
+        if near && !self.near {
+            warning!("Table '{}' is near its limit: {} of {} entries ({} evicted so far)",
+                     self.gate.table, len, max, self.evicted);
+        }
+        self.near = near;
+    }
+
+    fn shadow(&mut self, timestamp: DateTime<Local>, addr: IpAddr) {
+        let decisions = match self.shadow {
+            Some(ref mut counter) => counter.count(timestamp, addr),
+            None                  => return,
+        };
+
+        // report each address at most once per shadow period
+        let period = self.gate.shadow.map_or(Duration::zero(), |rules| rules.period);
+        let seen   = timestamp.with_timezone(&UTC);
+        for decision in decisions {
+            let cidr = decision.cidr;
+            let report = match self.shadowed.get(&cidr) {
+                Some(&reported) => seen - reported >= period,
+                None            => true,
+            };
+            if report {
+                self.shadowed.insert(cidr, seen);
+                let live = match self.bans.contains(&cidr) {
+                    true  => "blocked",
+                    false => "not blocked",
+                };
+                syslog!("Shadow rules would block {} (count {}, rule {}, live {})",
+                        cidr, decision.count, decision.rule, live);
+            }
+        }
+    }
+
+    fn expire(&mut self, now: DateTime<UTC>) -> Result<(), io::Error> {
+        let table   = self.gate.table;
+        let expired = self.bans.expire(now);
+        let changed = !expired.is_empty();
+        for cidr in &try!(self.keep_active(expired, now)) {
+            self.blocked.remove(cidr);
+            if try!(self.update(|blocker| blocker.remove(table, &[*cidr]))) == 1 {
+                if self.gate.dry_run {
+                    syslog!("Would unblock {}", cidr);
+                } else {
+                    syslog!("Address removed from table '{}': {}", table, cidr);
+                }
+            }
+        }
+        if changed {
+            self.changed();
+        }
+
+        // forget shadow reports at most once per shadow period
+        if let Some(ref rules) = self.gate.shadow {
+            if now - self.pruned >= rules.period {
+                let stale: Vec<Cidr> = self.shadowed.iter().filter(|&(_, &seen)| now - seen >= rules.period).map(|(cidr, _)| *cidr).collect();
+                for cidr in &stale {
+                    self.shadowed.remove(cidr);
+                }
+                self.pruned = now;
+            }
+        }
+
+        if let Some(ref mut snapshot) = self.snapshot {
+            if snapshot.due(now) {
+                let entries: Vec<Cidr> = self.bans.entries().into_iter().map(|(cidr, _)| cidr).collect();
+                if let Err(e) = snapshot.flush(now, &entries) {
+                    syslog!("Failed to write table file: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Apply a table update, recreating the table and retrying once when
+    // it has disappeared, e.g. after a ruleset reload.
+    fn update<T, F>(&mut self, f: F) -> Result<T, io::Error> where F: Fn(&mut B) -> Result<T, io::Error> {
+        match f(&mut self.blocker) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                try!(self.recreate());
+                f(&mut self.blocker)
+            },
+            result => result,
+        }
+    }
+
+    fn recreate(&mut self) -> Result<(), io::Error> {
+        let table = self.gate.table;
+        try!(self.blocker.ensure_table(table));
+
+        let now = UTC::now();
+        let mut n = 0;
+        for (cidr, expires) in self.bans.entries() {
+            match expires {
+                Some(when) if when <= now => continue,
+                _                         => (),
+            }
+            n += try!(self.blocker.add(table, &[cidr], expires.map(|when| when - now)));
+        }
+
+        warning!("Table '{}' disappeared, recreated it with {} addresses", table, n);
+        Ok(())
+    }
+
+    // Extend the bans of expired addresses the firewall is still blocking
+    // traffic from, returning the ones that should be removed.
+    fn keep_active(&mut self, expired: Vec<Cidr>, now: DateTime<UTC>) -> Result<Vec<Cidr>, io::Error> {
+        if self.gate.keep_active == 0 || expired.is_empty() {
+            return Ok(expired);
+        }
+
+        let stats = try!(self.blocker.stats(self.gate.table));
+        let packets: HashMap<Cidr, u64> = stats.into_iter().map(|stats| (stats.cidr, stats.packets)).collect();
+
+        let mut remove = Vec::new();
+        for cidr in expired {
+            let total = packets.get(&cidr).cloned().unwrap_or(0);
+            let since = total - cmp::min(total, self.blocked.get(&cidr).cloned().unwrap_or(0));
+            if since < self.gate.keep_active {
+                remove.push(cidr);
+                continue;
+            }
+
+            let level = cmp::max(self.bans.offenses(&cidr, now), 1) - 1;
+            let ban   = self.gate.ban_time(level);
+            self.bans.schedule(cidr, ban.map(|d| now + d));
+            self.blocked.insert(cidr, total);
+            syslog!("Ban extended on {}: {} packets blocked ({})", cidr, since, describe(ban));
+        }
+        Ok(remove)
+    }
+
+    fn restore(&mut self) -> Result<(), io::Error> {
+        let table = self.gate.table;
+        let state = match self.gate.store.as_ref().map(Store::load) {
+            Some(Err(ref e)) if e.kind() == io::ErrorKind::InvalidData => {
+                warning!("Starting without saved bans: {}", e);
+                None
+            },
+            Some(state) => try!(state),
+            None        => None,
+        };
+
+        if let Some(state) = state {
+            state.restore(&mut self.bans);
+            self.evicted = state.evicted.unwrap_or(0);
+
+            let now = UTC::now();
+            let active: Vec<(Cidr, Option<DateTime<UTC>>)> = self.bans.entries().into_iter().filter(|&(cidr, expires)| {
+                match expires {
+                    _ if self.gate.allow.overlaps(cidr)     => false,
+                    _ if self.lockout.check(cidr).is_some() => false,
+                    Some(when) if when <= now               => false,
+                    _                                       => true,
+                }
+            }).collect();
+
+            if self.gate.resync {
+                let addrs: Vec<(Cidr, Option<Duration>)> = active.iter().map(|&(cidr, expires)| {
+                    (cidr, expires.map(|when| when - now))
+                }).collect();
+                let changes = try!(self.blocker.replace(table, &addrs));
+                syslog!("Resynced table '{}': {} added, {} removed, {} changed",
+                        table, changes.added, changes.removed, changes.changed);
+                return Ok(());
+            }
+
+            let present: HashSet<Cidr> = try!(self.blocker.list(table)).into_iter().collect();
+
+            let mut n = 0;
+            for (cidr, expires) in active {
+                if !present.contains(&cidr) {
+                    n += try!(self.blocker.add(table, &[cidr], expires.map(|when| when - now)));
+                }
+            }
+
+            if n > 0 {
+                syslog!("Restored {} addresses to table '{}'", n, table);
+            }
+        }
+        Ok(())
+    }
+
+    fn changed(&mut self) {
+        if let Some(ref mut snapshot) = self.snapshot {
+            snapshot.changed(UTC::now());
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        if self.gate.dry_run {
+            return;
+        }
+
+        if let Some(ref store) = self.gate.store {
+            let mut state = State::capture(&self.bans);
+            state.evicted = Some(self.evicted);
+            if let Err(e) = store.save(&state) {
+                syslog!("Failed to save state: {}", e);
+            }
+        }
     }
 }
 
@@ -96,6 +911,238 @@ static PATTERNS: [Regex; 3] = [
     regex!(r"sshd\[\d+\]: Received disconnect from (?P<addr>.+?):.+\[preauth\]"),
 ];
 
+fn ban_times(list: &str) -> Result<Vec<Option<Duration>>, docopt::Error> {
+    let mut times = Vec::new();
+    for item in list.split(',') {
+        match item.trim().parse::<i64>() {
+            Ok(0)          => times.push(None),
+            Ok(n) if n > 0 => times.push(Some(Duration::minutes(n))),
+            _              => return Err(docopt::Error::Argv(format!("invalid ban time: {}", item))),
+        }
+    }
+    Ok(times)
+}
+
+fn allowlist(list: &str) -> Result<Allowlist, docopt::Error> {
+    let mut allow = Allowlist::new();
+    for item in list.split(',').map(|item| item.trim()).filter(|item| !item.is_empty()) {
+        match item.parse() {
+            Ok(cidr) => allow.insert(cidr),
+            Err(e)   => return Err(docopt::Error::Argv(format!("invalid allow entry {}: {}", item, e))),
+        }
+    }
+    Ok(allow)
+}
+
+fn load_allowlist(allow: &mut Allowlist, args: &Args) -> Result<(), io::Error> {
+    if let Some(ref file) = args.flag_allow_file {
+        try!(allow.load(file));
+    }
+    if let Some(ref table) = args.flag_allow_table {
+        let pf = try!(Pf::with_anchor(args.flag_anchor.as_ref().map(|a| &a[..])));
+        for addr in try!(pf.addrs(table)) {
+            if !addr.is_negated() {
+                allow.insert(addr.as_cidr());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn table_flags(list: &str) -> Result<TableFlags, docopt::Error> {
+    let mut flags = TableFlags::empty();
+    for item in list.split(',').map(|item| item.trim()).filter(|item| !item.is_empty()) {
+        flags = flags | match item {
+            "persist"  => pf::PFR_TFLAG_PERSIST,
+            "const"    => pf::PFR_TFLAG_CONST,
+            "counters" => pf::PFR_TFLAG_COUNTERS,
+            _          => return Err(docopt::Error::Argv(format!("invalid table flag: {}", item))),
+        };
+    }
+    Ok(flags)
+}
+
+fn policy(name: &str) -> Result<Policy, docopt::Error> {
+    name.parse().map_err(docopt::Error::Argv)
+}
+
+fn privsep(args: &Args) -> Result<Option<(String, PathBuf)>, docopt::Error> {
+    let user = match args.flag_user {
+        Some(ref user) => user.clone(),
+        None           => return Ok(None),
+    };
+
+    // these need to read the table, which only the privileged parent can
+    let unsupported = [
+        (args.flag_resync,                 "--resync"),
+        (args.flag_kill_states,            "--kill-states"),
+        (args.flag_keep_active > 0,        "--keep-active"),
+        (args.flag_evict == "fewest-hits", "--evict fewest-hits"),
+    ];
+    for &(set, flag) in &unsupported {
+        if set {
+            return Err(docopt::Error::Argv(format!("{} cannot be combined with --user", flag)));
+        }
+    }
+
+    let cwd  = env::current_dir().unwrap_or(PathBuf::from("/"));
+    let root = match args.flag_chroot {
+        Some(ref dir) => cwd.join(dir),
+        None          => cwd.join(&args.arg_logfile).parent().map_or(PathBuf::from("/"), PathBuf::from),
+    };
+
+    // the child writes these after entering the chroot
+    for path in args.flag_state.iter().chain(args.flag_table_file.iter()) {
+        if privsep::confined(&cwd.join(path), &root).is_none() {
+            return Err(docopt::Error::Argv(format!("{} is outside the chroot {}", path, root.display())));
+        }
+    }
+    Ok(Some((user, root)))
+}
+
+// A path the log parsing process opens, as seen from inside its chroot.
+fn inside(args: &Args, path: &str) -> String {
+    match privsep(args) {
+        Ok(Some((_, ref root))) if !args.flag_dry_run => {
+            let cwd = env::current_dir().unwrap_or(PathBuf::from("/"));
+            match privsep::confined(&cwd.join(path), root) {
+                Some(path) => path.to_string_lossy().into_owned(),
+                None       => path.to_string(),
+            }
+        },
+        _                                             => path.to_string(),
+    }
+}
+
+fn sandbox(args: &Args) -> Result<bool, docopt::Error> {
+    if !args.flag_sandbox {
+        return Ok(false);
+    }
+    if !cfg!(target_os = "linux") {
+        return Err(docopt::Error::Argv("--sandbox is only supported on Linux".to_string()));
+    }
+    // running commands needs execve, which the sandbox forbids, and
+    // rewriting hosts.deny would need write access to all of /etc
+    match (&args.flag_backend[..], &args.flag_user) {
+        ("exec", &None) | ("hosts-deny", &None) => {
+            let msg = format!("--sandbox requires --user with the {} backend", args.flag_backend);
+            return Err(docopt::Error::Argv(msg));
+        },
+        _ => (),
+    }
+    Ok(true)
+}
+
+fn lockout() -> Lockout {
+    Lockout::detect().unwrap_or_else(|e| {
+        warning!("Failed to detect local addresses: {}", e);
+        Lockout::new()
+    })
+}
+
+fn backend(args: &Args) -> Result<Backend, docopt::Error> {
+    if args.flag_kill_states && args.flag_backend != "pf" {
+        return Err(docopt::Error::Argv("--kill-states requires the pf backend".to_string()));
+    }
+
+    match &args.flag_backend[..] {
+        "pf"         => {
+            // pf names are fixed size buffers, reject what would not fit
+            let anchor = args.flag_anchor.as_ref().map(|a| &a[..]);
+            if let Err(e) = pf::Table::new(&args.flag_table, anchor) {
+                return Err(docopt::Error::Argv(e.to_string()));
+            }
+            Ok(Backend::Pf(args.flag_anchor.clone(), try!(table_flags(&args.flag_table_flags))))
+        },
+        "nft"        => Ok(Backend::Nft(args.flag_nft_family.clone(), args.flag_nft_table.clone())),
+        "ipset"      => {
+            // hash:ip sets reject the prefix entries these produce
+            let prefixes = args.flag_prefix_limit > 0 || args.flag_evict == "collapse";
+            if prefixes && args.flag_ipset_type == "hash:ip" {
+                return Err(docopt::Error::Argv("prefix bans require --ipset-type hash:net".to_string()));
+            }
+            Ok(Backend::Ipset(args.flag_ipset_type.clone()))
+        },
+        "exec"       => match args.flag_exec_ban {
+            // bans that end or get evicted need a way to be lifted
+            Some(..) if args.flag_exec_unban.is_none() && removes(args) => {
+                Err(docopt::Error::Argv("--exec-unban is required unless bans are permanent".to_string()))
+            },
+            Some(ref ban) => Ok(Backend::Exec(exec::Config {
+                ban:       ban.clone(),
+                unban:     args.flag_exec_unban.clone(),
+                list:      args.flag_exec_list.clone(),
+                timeout:   args.flag_exec_timeout,
+                retries:   args.flag_exec_retries,
+                backoff:   args.flag_exec_backoff,
+                jobs:      args.flag_exec_jobs,
+                unchanged: args.flag_exec_unchanged,
+            })),
+            None          => Err(docopt::Error::Argv("exec backend requires --exec-ban".to_string())),
+        },
+        "hosts-deny" => Ok(Backend::HostsDeny(args.flag_deny_file.clone(), args.flag_deny_daemons.clone())),
+        name         => Err(docopt::Error::Argv(format!("unknown backend: {}", name))),
+    }
+}
+
+fn removes(args: &Args) -> bool {
+    let finite = ban_times(&args.flag_ban_time).map(|times| times.iter().any(Option::is_some));
+    finite.unwrap_or(false) || args.flag_max_entries > 0
+}
+
+fn status(backend: &Backend, table: &str, store: Option<&Store>) -> Result<(), io::Error> {
+    if let Some(state) = try!(store.map_or(Ok(None), |store| store.load())) {
+        println!("table {}: {} entries evicted at --max-entries", table, state.evicted.unwrap_or(0));
+    }
+
+    match *backend {
+        Backend::Pf(ref anchor, _)                => {
+            let pf = try!(Pf::with_anchor(anchor.as_ref().map(|a| &a[..])));
+            for stats in try!(pf.table_stats()).iter().filter(|stats| stats.table().name() == table) {
+                println!("table {}: {} addresses, {} packets / {} bytes blocked since {}",
+                         table, stats.len(), stats.blocked_packets(), stats.blocked_bytes(), stats.since());
+            }
+            report(pf, table)
+        },
+        Backend::Nft(ref family, ref nft_table)   => report(try!(Nft::new(family, nft_table)), table),
+        Backend::Ipset(ref kind)                  => report(try!(Ipset::new(kind)), table),
+        Backend::Exec(ref config)                 => report(try!(Exec::new(config.clone())), table),
+        Backend::HostsDeny(ref file, ref daemons) => report(HostsDeny::new(file, daemons), table),
+    }
+}
+
+// Print each entry with its counters, or just the entries for firewalls
+// that don't count blocked traffic.
+fn report<B: Blocker>(mut blocker: B, table: &str) -> Result<(), io::Error> {
+    let stats = try!(blocker.stats(table));
+    if stats.is_empty() {
+        for cidr in try!(blocker.list(table)) {
+            println!("{}", cidr);
+        }
+        return Ok(());
+    }
+
+    for stats in stats {
+        let added = stats.added.map(|when| format!("  added {}", when)).unwrap_or(String::new());
+        println!("{:<43} {:>10} packets {:>12} bytes{}", stats.cidr.to_string(), stats.packets, stats.bytes, added);
+    }
+    Ok(())
+}
+
+fn describe(ban: Option<Duration>) -> String {
+    match ban {
+        Some(d) => format!("{} minutes", d.num_minutes()),
+        None    => "permanent".to_string(),
+    }
+}
+
+fn parse(line: &str) -> Option<(DateTime<Local>, IpAddr)> {
+    match timestamp(line, Local::now()) {
+        Ok(timestamp) => matches(line).map(|addr| (timestamp, addr)),
+        Err(..)       => None,
+    }
+}
+
 fn matches(line: &str) -> Option<IpAddr> {
     for regex in PATTERNS.iter() {
         if let Some(cap) = regex.captures(line) {
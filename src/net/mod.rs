@@ -0,0 +1,162 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fmt::{self, Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Cidr {
+    addr: IpAddr,
+    len:  u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, len: u8) -> Cidr {
+        let len = clamp_len(addr, len);
+        Cidr {
+            addr: mask(addr, len),
+            len:  len,
+        }
+    }
+
+    pub fn host(addr: IpAddr) -> Cidr {
+        Cidr::new(addr, max_len(addr))
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn len(&self) -> u8 {
+        self.len
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.len == max_len(self.addr)
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr) {
+            (IpAddr::V4(..), IpAddr::V4(..)) => mask(addr, self.len) == self.addr,
+            (IpAddr::V6(..), IpAddr::V6(..)) => mask(addr, self.len) == self.addr,
+            _                                => false,
+        }
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.is_host() {
+            true  => write!(fmt, "{}", self.addr),
+            false => write!(fmt, "{}/{}", self.addr, self.len),
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+
+    fn from_str(s: &str) -> Result<Cidr, CidrError> {
+        let mut parts = s.splitn(2, '/');
+        let addr: IpAddr = match parts.next().unwrap().parse() {
+            Ok(addr) => addr,
+            Err(..)  => return Err(CidrError::InvalidAddr),
+        };
+        let len = match parts.next().map(|s| s.parse::<u8>()) {
+            Some(Ok(len)) if len <= max_len(addr) => len,
+            Some(..)                              => return Err(CidrError::InvalidPrefix),
+            None                                  => max_len(addr),
+        };
+        let cidr = Cidr::new(addr, len);
+        match cidr.addr == addr {
+            true  => Ok(cidr),
+            false => Err(CidrError::HostBits),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CidrError {
+    InvalidAddr,
+    InvalidPrefix,
+    HostBits,
+}
+
+impl Display for CidrError {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        fmt.write_str(match *self {
+            CidrError::InvalidAddr   => "invalid address",
+            CidrError::InvalidPrefix => "invalid prefix length",
+            CidrError::HostBits      => "host bits set in network address",
+        })
+    }
+}
+
+pub fn octets(addr: IpAddr) -> Vec<u8> {
+    match addr {
+        IpAddr::V4(v4) => v4.octets().to_vec(),
+        IpAddr::V6(v6) => {
+            let mut bytes = Vec::with_capacity(16);
+            for word in v6.segments().iter() {
+                bytes.push((word >> 8) as u8);
+                bytes.push(*word as u8);
+            }
+            bytes
+        },
+    }
+}
+
+pub fn from_octets(b: &[u8]) -> Option<IpAddr> {
+    let w = |n: usize| (b[n] as u16) << 8 | b[n + 1] as u16;
+    match b.len() {
+        4  => Some(IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3]))),
+        16 => Some(IpAddr::V6(Ipv6Addr::new(w(0), w(2), w(4), w(6), w(8), w(10), w(12), w(14)))),
+        _  => None,
+    }
+}
+
+pub fn max_len(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(..) => 32,
+        IpAddr::V6(..) => 128,
+    }
+}
+
+fn clamp_len(addr: IpAddr, len: u8) -> u8 {
+    match max_len(addr) {
+        max if len > max => max,
+        _                => len,
+    }
+}
+
+fn mask(addr: IpAddr, len: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(v4) => {
+            let o = v4.octets();
+            let mut w = [o[0] as u16, o[1] as u16, o[2] as u16, o[3] as u16];
+            mask_words(&mut w, 8, len as u32);
+            IpAddr::V4(Ipv4Addr::new(w[0] as u8, w[1] as u8, w[2] as u8, w[3] as u8))
+        },
+        IpAddr::V6(v6) => {
+            let mut w = v6.segments();
+            mask_words(&mut w, 16, len as u32);
+            IpAddr::V6(Ipv6Addr::new(w[0], w[1], w[2], w[3], w[4], w[5], w[6], w[7]))
+        },
+    }
+}
+
+fn mask_words(words: &mut [u16], width: u32, len: u32) {
+    for (i, word) in words.iter_mut().enumerate() {
+        let start = i as u32 * width;
+        let keep = match len {
+            n if n >= start + width => width,
+            n if n > start          => n - start,
+            _                       => 0,
+        };
+        let ones = (1u32 << width) - 1;
+        *word &= (ones ^ ((1u32 << (width - keep)) - 1)) as u16;
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,77 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::net::IpAddr;
+use super::{Cidr, CidrError, octets, from_octets};
+
+#[test]
+fn mask_ipv4() {
+    let cidr = Cidr::new(addr("203.0.113.77"), 24);
+    assert_eq!(addr("203.0.113.0"), cidr.addr());
+    assert_eq!(24, cidr.len());
+    assert_eq!("203.0.113.0/24", cidr.to_string());
+
+    assert_eq!(addr("203.0.112.0"), Cidr::new(addr("203.0.113.77"), 20).addr());
+    assert_eq!(addr("0.0.0.0"),     Cidr::new(addr("203.0.113.77"), 0).addr());
+}
+
+#[test]
+fn mask_ipv6() {
+    let cidr = Cidr::new(addr("2001:db8:1:2:3:4:5:6"), 64);
+    assert_eq!(addr("2001:db8:1:2::"), cidr.addr());
+    assert_eq!("2001:db8:1:2::/64", cidr.to_string());
+
+    assert_eq!(addr("2001:c00::"), Cidr::new(addr("2001:db8::1"), 23).addr());
+}
+
+#[test]
+fn host() {
+    let v4 = Cidr::host(addr("1.2.3.4"));
+    let v6 = Cidr::host(addr("2404:6800:4004:814::200e"));
+
+    assert!(v4.is_host() && v6.is_host());
+    assert_eq!(32,  v4.len());
+    assert_eq!(128, v6.len());
+    assert_eq!("1.2.3.4", v4.to_string());
+    assert_eq!(32, Cidr::new(addr("1.2.3.4"), 99).len());
+}
+
+#[test]
+fn contains() {
+    let cidr = Cidr::new(addr("2001:db8::"), 64);
+    assert!(cidr.contains(addr("2001:db8::1")));
+    assert!(cidr.contains(addr("2001:db8::ffff:ffff:ffff:ffff")));
+    assert!(!cidr.contains(addr("2001:db8:0:1::1")));
+    assert!(!cidr.contains(addr("32.1.13.184")));
+}
+
+#[test]
+fn parse() {
+    assert_eq!(Ok(Cidr::new(addr("203.0.113.0"), 24)), "203.0.113.0/24".parse());
+    assert_eq!(Ok(Cidr::new(addr("2001:db8::"), 48)),  "2001:db8::/48".parse());
+    assert_eq!(Ok(Cidr::host(addr("1.2.3.4"))),        "1.2.3.4".parse());
+}
+
+#[test]
+fn parse_invalid() {
+    assert_eq!(Err(CidrError::InvalidAddr),   "203.0.113/24".parse::<Cidr>());
+    assert_eq!(Err(CidrError::InvalidPrefix), "203.0.113.0/33".parse::<Cidr>());
+    assert_eq!(Err(CidrError::InvalidPrefix), "203.0.113.0/".parse::<Cidr>());
+    assert_eq!(Err(CidrError::HostBits),      "203.0.113.1/24".parse::<Cidr>());
+    assert_eq!(Err(CidrError::HostBits),      "2001:db8::1/64".parse::<Cidr>());
+}
+
+#[test]
+fn octets_round_trip() {
+    let v4 = addr("203.0.113.7");
+    let v6 = addr("2001:db8::1:2");
+
+    assert_eq!(vec![203, 0, 113, 7], octets(v4));
+    assert_eq!(vec![0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2], octets(v6));
+    assert_eq!(Some(v4), from_octets(&octets(v4)));
+    assert_eq!(Some(v6), from_octets(&octets(v6)));
+    assert_eq!(None, from_octets(&[1, 2, 3]));
+}
+
+fn addr(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
@@ -0,0 +1,366 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::io::{Error, ErrorKind};
+use std::mem;
+
+use libc::funcs::bsd43::{bind, recv, send, socket};
+use libc::funcs::posix88::unistd::close;
+use libc::types::common::c95::c_void;
+use libc::types::os::arch::c95::{c_int, size_t};
+use libc::types::os::common::bsd44::{sockaddr, socklen_t};
+
+pub const AF_NETLINK:        c_int = 16;
+pub const SOCK_RAW:          c_int = 3;
+pub const NETLINK_NETFILTER: c_int = 12;
+
+pub const NLMSG_ERROR: u16 = 2;
+pub const NLMSG_DONE:  u16 = 3;
+
+pub const NLM_F_REQUEST: u16 = 0x001;
+pub const NLM_F_MULTI:   u16 = 0x002;
+pub const NLM_F_ACK:     u16 = 0x004;
+pub const NLM_F_DUMP:    u16 = 0x300;
+pub const NLM_F_EXCL:    u16 = 0x200;
+pub const NLM_F_CREATE:  u16 = 0x400;
+
+pub const NLA_F_NESTED:        u16 = 0x8000;
+pub const NLA_F_NET_BYTEORDER: u16 = 0x4000;
+pub const NLA_TYPE_MASK:       u16 = !(NLA_F_NESTED | NLA_F_NET_BYTEORDER);
+
+pub const NFNETLINK_V0:         u8  = 0;
+pub const NFNL_SUBSYS_IPSET:    u16 = 6;
+pub const NFNL_SUBSYS_NFTABLES: u16 = 10;
+pub const NFNL_MSG_BATCH_BEGIN: u16 = 16;
+pub const NFNL_MSG_BATCH_END:   u16 = 17;
+
+const NLMSG_HDRLEN: usize = 16;
+const NLA_HDRLEN:   usize = 4;
+const NFGENMSG_LEN: usize = 4;
+
+pub struct Msg {
+    buf:   Vec<u8>,
+    nests: Vec<usize>,
+}
+
+impl Msg {
+    pub fn new(kind: u16, flags: u16, seq: u32) -> Msg {
+        let mut buf = Vec::with_capacity(64);
+        put_u32(&mut buf, 0);
+        put_u16(&mut buf, kind);
+        put_u16(&mut buf, flags);
+        put_u32(&mut buf, seq);
+        put_u32(&mut buf, 0);
+        Msg { buf: buf, nests: Vec::new() }
+    }
+
+    pub fn nfgen(kind: u16, flags: u16, seq: u32, family: u8, res_id: u16) -> Msg {
+        let mut msg = Msg::new(kind, flags, seq);
+        msg.buf.push(family);
+        msg.buf.push(NFNETLINK_V0);
+        put_u16(&mut msg.buf, res_id.to_be());
+        msg
+    }
+
+    pub fn put(&mut self, kind: u16, data: &[u8]) -> &mut Msg {
+        put_u16(&mut self.buf, (NLA_HDRLEN + data.len()) as u16);
+        put_u16(&mut self.buf, kind);
+        self.buf.push_all(data);
+        while self.buf.len() % 4 != 0 {
+            self.buf.push(0);
+        }
+        self
+    }
+
+    pub fn put_str(&mut self, kind: u16, s: &str) -> &mut Msg {
+        let mut data = s.as_bytes().to_vec();
+        data.push(0);
+        self.put(kind, &data)
+    }
+
+    pub fn put_u8(&mut self, kind: u16, v: u8) -> &mut Msg {
+        self.put(kind, &[v])
+    }
+
+    pub fn put_be32(&mut self, kind: u16, v: u32) -> &mut Msg {
+        let mut data = Vec::with_capacity(4);
+        put_u32(&mut data, v.to_be());
+        self.put(kind, &data)
+    }
+
+    pub fn put_be64(&mut self, kind: u16, v: u64) -> &mut Msg {
+        let bytes: [u8; 8] = unsafe { mem::transmute(v.to_be()) };
+        self.put(kind, &bytes)
+    }
+
+    pub fn begin(&mut self, kind: u16) -> &mut Msg {
+        self.nests.push(self.buf.len());
+        put_u16(&mut self.buf, 0);
+        put_u16(&mut self.buf, kind | NLA_F_NESTED);
+        self
+    }
+
+    pub fn end(&mut self) -> &mut Msg {
+        let start = self.nests.pop().expect("unbalanced nested attribute");
+        let len = self.buf.len() - start;
+        set_u16(&mut self.buf, start, len as u16);
+        self
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let len = self.buf.len();
+        set_u32(&mut self.buf, 0, len as u32);
+        self.buf
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Header {
+    pub len:   u32,
+    pub kind:  u16,
+    pub flags: u16,
+    pub seq:   u32,
+    pub pid:   u32,
+}
+
+pub fn messages(buf: &[u8]) -> Result<Vec<(Header, &[u8])>, Error> {
+    let mut msgs = Vec::new();
+    let mut pos  = 0;
+
+    while pos + NLMSG_HDRLEN <= buf.len() {
+        let hdr = Header {
+            len:   get_u32(&buf[pos..]),
+            kind:  get_u16(&buf[pos + 4..]),
+            flags: get_u16(&buf[pos + 6..]),
+            seq:   get_u32(&buf[pos + 8..]),
+            pid:   get_u32(&buf[pos + 12..]),
+        };
+
+        let len = hdr.len as usize;
+        if len < NLMSG_HDRLEN || pos + len > buf.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, "truncated netlink message"));
+        }
+
+        msgs.push((hdr, &buf[pos + NLMSG_HDRLEN..pos + len]));
+        pos += align(len);
+    }
+
+    Ok(msgs)
+}
+
+pub fn attrs(buf: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attrs = Vec::new();
+    let mut pos   = 0;
+
+    while pos + NLA_HDRLEN <= buf.len() {
+        let len  = get_u16(&buf[pos..]) as usize;
+        let kind = get_u16(&buf[pos + 2..]) & NLA_TYPE_MASK;
+        if len < NLA_HDRLEN || pos + len > buf.len() {
+            break;
+        }
+        attrs.push((kind, &buf[pos + NLA_HDRLEN..pos + len]));
+        pos += align(len);
+    }
+
+    attrs
+}
+
+// The attributes following the struct nfgenmsg header of a reply.
+pub fn nfgen_attrs(payload: &[u8]) -> Result<&[u8], Error> {
+    match payload.len() {
+        len if len < NFGENMSG_LEN => Err(Error::new(ErrorKind::InvalidInput, "truncated nfnetlink message")),
+        _                         => Ok(&payload[NFGENMSG_LEN..]),
+    }
+}
+
+pub fn attr(buf: &[u8], kind: u16) -> Option<&[u8]> {
+    attrs(buf).into_iter().find(|&(k, _)| k == kind).map(|(_, data)| data)
+}
+
+pub fn error(payload: &[u8]) -> i32 {
+    get_u32(payload) as i32
+}
+
+// Record the error code of each ack for requests `first` onwards in
+// `codes`, 0 for success, returning how many were newly acknowledged.
+pub fn acks(buf: &[u8], first: u32, codes: &mut [Option<i32>]) -> Result<usize, Error> {
+    let mut n = 0;
+    for (hdr, payload) in try!(messages(buf)) {
+        if hdr.kind != NLMSG_ERROR || hdr.seq < first {
+            continue;
+        }
+        if payload.len() < 4 {
+            return Err(Error::new(ErrorKind::InvalidInput, "truncated netlink error"));
+        }
+        match codes.get_mut((hdr.seq - first) as usize) {
+            Some(code) if code.is_none() => {
+                *code = Some(-error(payload));
+                n += 1;
+            },
+            _                            => (),
+        }
+    }
+    Ok(n)
+}
+
+pub fn be32(data: &[u8]) -> u32 {
+    (data[0] as u32) << 24 | (data[1] as u32) << 16 | (data[2] as u32) << 8 | data[3] as u32
+}
+
+pub struct Socket {
+    fd:  c_int,
+    seq: u32,
+}
+
+#[repr(C)]
+struct SockaddrNl {
+    nl_family: u16,
+    nl_pad:    u16,
+    nl_pid:    u32,
+    nl_groups: u32,
+}
+
+impl Socket {
+    pub fn open(protocol: c_int) -> Result<Socket, Error> {
+        unsafe {
+            let fd = match socket(AF_NETLINK, SOCK_RAW, protocol) {
+                -1 => return Err(Error::last_os_error()),
+                fd => fd,
+            };
+
+            let mut addr: SockaddrNl = mem::zeroed();
+            addr.nl_family = AF_NETLINK as u16;
+
+            let ptr = &addr as *const _ as *const sockaddr;
+            let len = mem::size_of::<SockaddrNl>() as socklen_t;
+            if bind(fd, ptr, len) == -1 {
+                let err = Error::last_os_error();
+                close(fd);
+                return Err(err);
+            }
+
+            Ok(Socket { fd: fd, seq: 0 })
+        }
+    }
+
+    pub fn next_seq(&mut self) -> u32 {
+        self.seq = self.seq.wrapping_add(1);
+        self.seq
+    }
+
+    // Send a request and collect reply payloads until `acks` messages
+    // with sequence numbers >= `first` are acknowledged, or until the
+    // end of a dump when `acks` is 0.
+    pub fn execute(&self, buf: &[u8], first: u32, acks: usize) -> Result<Vec<Vec<u8>>, Error> {
+        try!(self.send(buf));
+
+        let mut replies = Vec::new();
+        let mut pending = acks;
+
+        loop {
+            let data = try!(self.recv());
+            for (hdr, payload) in try!(messages(&data)) {
+                if hdr.seq < first {
+                    continue;
+                }
+                match hdr.kind {
+                    NLMSG_ERROR if payload.len() < 4 => {
+                        return Err(Error::new(ErrorKind::InvalidData, "truncated netlink error"));
+                    },
+                    NLMSG_ERROR => match error(payload) {
+                        0     => pending = pending.saturating_sub(1),
+                        errno => return Err(Error::from_raw_os_error(-errno)),
+                    },
+                    NLMSG_DONE => return Ok(replies),
+                    _          => replies.push(payload.to_vec()),
+                }
+            }
+            if acks > 0 && pending == 0 {
+                return Ok(replies);
+            }
+        }
+    }
+
+    // Send `count` requests with consecutive sequence numbers from `first`
+    // that each ask for an ack, returning the error code of each.
+    pub fn execute_each(&self, buf: &[u8], first: u32, count: usize) -> Result<Vec<i32>, Error> {
+        try!(self.send(buf));
+
+        let mut codes   = vec![None; count];
+        let mut pending = count;
+        while pending > 0 {
+            let data = try!(self.recv());
+            pending = pending.saturating_sub(try!(acks(&data, first, &mut codes)));
+        }
+
+        Ok(codes.into_iter().map(|code| code.unwrap_or(0)).collect())
+    }
+
+    fn send(&self, buf: &[u8]) -> Result<(), Error> {
+        unsafe {
+            match send(self.fd, buf.as_ptr() as *const c_void, buf.len() as size_t, 0) {
+                -1 => Err(Error::last_os_error()),
+                 _ => Ok(()),
+            }
+        }
+    }
+
+    fn recv(&self) -> Result<Vec<u8>, Error> {
+        let mut buf: Vec<u8> = Vec::with_capacity(65536);
+        unsafe {
+            let ptr = buf.as_mut_ptr() as *mut c_void;
+            match recv(self.fd, ptr, buf.capacity() as size_t, 0) {
+                -1 => Err(Error::last_os_error()),
+                 n => {
+                    buf.set_len(n as usize);
+                    Ok(buf)
+                },
+            }
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.fd);
+        }
+    }
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn put_u16(buf: &mut Vec<u8>, v: u16) {
+    let bytes: [u8; 2] = unsafe { mem::transmute(v) };
+    buf.push_all(&bytes);
+}
+
+fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    let bytes: [u8; 4] = unsafe { mem::transmute(v) };
+    buf.push_all(&bytes);
+}
+
+fn set_u16(buf: &mut Vec<u8>, at: usize, v: u16) {
+    let bytes: [u8; 2] = unsafe { mem::transmute(v) };
+    buf[at]     = bytes[0];
+    buf[at + 1] = bytes[1];
+}
+
+fn set_u32(buf: &mut Vec<u8>, at: usize, v: u32) {
+    let bytes: [u8; 4] = unsafe { mem::transmute(v) };
+    for (n, b) in bytes.iter().enumerate() {
+        buf[at + n] = *b;
+    }
+}
+
+fn get_u16(buf: &[u8]) -> u16 {
+    unsafe { mem::transmute([buf[0], buf[1]]) }
+}
+
+fn get_u32(buf: &[u8]) -> u32 {
+    unsafe { mem::transmute([buf[0], buf[1], buf[2], buf[3]]) }
+}
+
+#[cfg(test)]
+mod tests;
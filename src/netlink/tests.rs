@@ -0,0 +1,93 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use super::*;
+
+#[test]
+#[cfg(target_endian = "little")]
+fn encode_header() {
+    let msg = Msg::nfgen(0x0a0c, NLM_F_REQUEST | NLM_F_ACK, 7, 2, NFNL_SUBSYS_NFTABLES).finish();
+    assert_eq!(vec![
+        20, 0, 0, 0,  0x0c, 0x0a, 0x05, 0x00,  7, 0, 0, 0,  0, 0, 0, 0,
+        2, 0, 0, 10,
+    ], msg);
+}
+
+#[test]
+#[cfg(target_endian = "little")]
+fn encode_attrs() {
+    let mut msg = Msg::new(16, NLM_F_REQUEST, 1);
+    msg.put_str(1, "ab").put_be32(2, 0x01020304);
+    msg.begin(3).put_u8(1, 0xff).end();
+    let msg = msg.finish();
+
+    assert_eq!(vec![
+        44, 0, 0, 0,  16, 0, 1, 0,  1, 0, 0, 0,  0, 0, 0, 0,
+        7, 0, 1, 0,  b'a', b'b', 0, 0,
+        8, 0, 2, 0,  1, 2, 3, 4,
+        12, 0, 3, 0x80,  5, 0, 1, 0,  0xff, 0, 0, 0,
+    ], msg);
+}
+
+#[test]
+fn parse_round_trip() {
+    let mut msg = Msg::new(16, NLM_F_REQUEST, 9);
+    msg.put_str(1, "irongate").begin(2).put_be32(1, 42).end();
+    let mut buf = msg.finish();
+    buf.extend(Msg::new(NLMSG_DONE, NLM_F_MULTI, 9).finish().into_iter());
+
+    let msgs = messages(&buf).unwrap();
+    assert_eq!(2, msgs.len());
+    assert_eq!(16, msgs[0].0.kind);
+    assert_eq!(9,  msgs[0].0.seq);
+    assert_eq!(NLMSG_DONE, msgs[1].0.kind);
+
+    let payload = msgs[0].1;
+    assert_eq!(Some(&b"irongate\0"[..]), attr(payload, 1));
+
+    let nested = attr(payload, 2).unwrap();
+    assert_eq!(42, be32(attr(nested, 1).unwrap()));
+}
+
+#[test]
+fn parse_truncated() {
+    let mut buf = Msg::new(16, NLM_F_REQUEST, 1).finish();
+    buf[0] = 200;
+    assert!(messages(&buf).is_err());
+}
+
+#[test]
+#[cfg(target_endian = "little")]
+fn parse_unaligned_tail() {
+    let attrs_in = [5, 0, 1, 0, 0xff];
+    assert_eq!(vec![(1, &[0xff][..])], attrs(&attrs_in));
+
+    let mut buf = Msg::new(16, NLM_F_REQUEST, 1).finish();
+    buf.push(0);
+    buf[0] = 17;
+    assert_eq!(1, messages(&buf).unwrap().len());
+}
+
+#[test]
+#[cfg(target_endian = "little")]
+fn collect_acks() {
+    let ack = |seq: u8, errno: i32| {
+        let code = -errno as u32;
+        vec![
+            20, 0, 0, 0,  2, 0, 0, 0,  seq, 0, 0, 0,  0, 0, 0, 0,
+            code as u8, (code >> 8) as u8, (code >> 16) as u8, (code >> 24) as u8,
+        ]
+    };
+
+    let mut buf = ack(4, 0);
+    buf.extend(ack(6, 17).into_iter());
+    buf.extend(ack(9, 0).into_iter());
+    buf.extend(ack(5, 0).into_iter());
+
+    let mut codes = vec![None; 3];
+    assert_eq!(2, acks(&buf, 5, &mut codes).unwrap());
+    assert_eq!(vec![Some(0), Some(17), None], codes);
+    assert_eq!(0, acks(&buf, 5, &mut codes).unwrap());
+
+    let short = [18, 0, 0, 0,  2, 0, 0, 0,  7, 0, 0, 0,  0, 0, 0, 0,  0, 0];
+    assert!(acks(&short, 5, &mut codes).is_err());
+}
@@ -0,0 +1,171 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+mod msg;
+
+use std::collections::HashSet;
+use std::io::{Error, ErrorKind};
+use std::net::IpAddr;
+use chrono::Duration;
+
+use block::Blocker;
+use net::Cidr;
+use netlink::{Socket, NETLINK_NETFILTER};
+use self::msg::*;
+
+pub struct Nft {
+    sock:   Socket,
+    family: u8,
+    table:  String,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Add(Option<u64>),
+    Del,
+}
+
+impl Nft {
+    pub fn new(family: &str, table: &str) -> Result<Nft, Error> {
+        let family = match msg::family(family) {
+            Some(family) => family,
+            None         => return Err(Error::new(ErrorKind::InvalidInput, "unknown nftables family")),
+        };
+
+        Ok(Nft {
+            sock:   try!(Socket::open(NETLINK_NETFILTER)),
+            family: family,
+            table:  table.to_string(),
+        })
+    }
+
+    fn modify(&mut self, set: &str, addrs: &[Cidr], op: Op) -> Result<(), Error> {
+        let first = self.sock.next_seq();
+        let mut msgs = Vec::new();
+
+        for &(ref name, key) in &sets(set) {
+            let timeout = match op {
+                Op::Add(timeout) => timeout,
+                Op::Del          => None,
+            };
+            let elems: Vec<Elem> = addrs.iter().filter(|cidr| key_type(cidr) == key).flat_map(|cidr| {
+                elems(*cidr, timeout).into_iter()
+            }).collect();
+
+            if elems.is_empty() {
+                continue;
+            }
+
+            let seq = self.sock.next_seq();
+            msgs.push(match op {
+                Op::Add(..) => new_elems(seq, self.family, &self.table, name, &elems, true),
+                Op::Del     => del_elems(seq, self.family, &self.table, name, &elems),
+            });
+        }
+
+        self.commit(first, msgs)
+    }
+
+    // A batch is aborted by the first element that already exists, or
+    // no longer exists, so fall back to one element per batch to count
+    // the elements actually changed.
+    fn modify_each(&mut self, set: &str, addrs: &[Cidr], op: Op) -> Result<usize, Error> {
+        let mut n = 0;
+        for addr in addrs {
+            match self.modify(set, &[*addr], op) {
+                Ok(())                         => n += 1,
+                Err(ref e) if unchanged(e, op) => (),
+                Err(e)                         => return Err(e),
+            }
+        }
+        Ok(n)
+    }
+
+    fn update(&mut self, set: &str, addrs: &[Cidr], op: Op) -> Result<usize, Error> {
+        match self.modify(set, addrs, op) {
+            Ok(())                                            => Ok(addrs.len()),
+            Err(ref e) if unchanged(e, op) && addrs.len() > 1 => self.modify_each(set, addrs, op),
+            Err(ref e) if unchanged(e, op)                    => Ok(0),
+            Err(e)                                            => Err(e),
+        }
+    }
+
+    fn commit(&mut self, first: u32, msgs: Vec<Vec<u8>>) -> Result<(), Error> {
+        if msgs.is_empty() {
+            return Ok(());
+        }
+        let buf = batch(first, &msgs);
+        self.sock.execute(&buf, first, msgs.len()).map(|_| ())
+    }
+}
+
+impl Blocker for Nft {
+    fn ensure_table(&mut self, set: &str) -> Result<(), Error> {
+        let first = self.sock.next_seq();
+        let mut msgs = vec![new_table(first, self.family, &self.table)];
+
+        for &(ref name, key) in &sets(set) {
+            let seq = self.sock.next_seq();
+            msgs.push(new_set(seq, self.family, &self.table, name, key, seq));
+        }
+
+        self.commit(first, msgs)
+    }
+
+    fn add(&mut self, set: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<usize, Error> {
+        let op = Op::Add(timeout.map(|d| d.num_milliseconds() as u64));
+        self.update(set, addrs, op)
+    }
+
+    // A batch can't say which elements already existed, so one dump of
+    // the sets picks out the new ones and they go in a single batch.
+    fn add_each(&mut self, set: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<Vec<bool>, Error> {
+        let op = Op::Add(timeout.map(|d| d.num_milliseconds() as u64));
+        let present: HashSet<Cidr> = try!(self.list(set)).into_iter().collect();
+        let new: Vec<Cidr> = addrs.iter().filter(|cidr| !present.contains(cidr)).cloned().collect();
+
+        match self.modify(set, &new, op) {
+            Ok(())                         => Ok(addrs.iter().map(|cidr| !present.contains(cidr)).collect()),
+            Err(ref e) if unchanged(e, op) => addrs.iter().map(|cidr| self.update(set, &[*cidr], op).map(|n| n == 1)).collect(),
+            Err(e)                         => Err(e),
+        }
+    }
+
+    fn remove(&mut self, set: &str, addrs: &[Cidr]) -> Result<usize, Error> {
+        self.update(set, addrs, Op::Del)
+    }
+
+    fn list(&mut self, set: &str) -> Result<Vec<Cidr>, Error> {
+        let mut elems = Vec::new();
+        for &(ref name, _) in &sets(set) {
+            let seq = self.sock.next_seq();
+            let buf = get_elems(seq, self.family, &self.table, name);
+            for reply in try!(self.sock.execute(&buf, seq, 0)) {
+                elems.extend(try!(parse_elems(&reply)).into_iter());
+            }
+        }
+        Ok(cidrs(elems))
+    }
+}
+
+// nftables sets hold a single key type, so each irongate table maps
+// to a pair of sets named <table>_v4 and <table>_v6.
+fn sets(set: &str) -> [(String, KeyType); 2] {
+    [(format!("{}_v4", set), KeyType::Ipv4), (format!("{}_v6", set), KeyType::Ipv6)]
+}
+
+fn key_type(cidr: &Cidr) -> KeyType {
+    match cidr.addr() {
+        IpAddr::V4(..) => KeyType::Ipv4,
+        IpAddr::V6(..) => KeyType::Ipv6,
+    }
+}
+
+fn unchanged(e: &Error, op: Op) -> bool {
+    match op {
+        Op::Add(..) => e.kind() == ErrorKind::AlreadyExists,
+        Op::Del     => e.kind() == ErrorKind::NotFound,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,250 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::io::Error;
+use net::{self, Cidr};
+use netlink::*;
+
+pub const NFT_MSG_NEWTABLE:   u16 = 0;
+pub const NFT_MSG_NEWSET:     u16 = 9;
+pub const NFT_MSG_NEWSETELEM: u16 = 12;
+pub const NFT_MSG_GETSETELEM: u16 = 13;
+pub const NFT_MSG_DELSETELEM: u16 = 14;
+
+const NFTA_TABLE_NAME: u16 = 1;
+
+const NFTA_SET_TABLE:    u16 = 1;
+const NFTA_SET_NAME:     u16 = 2;
+const NFTA_SET_FLAGS:    u16 = 3;
+const NFTA_SET_KEY_TYPE: u16 = 4;
+const NFTA_SET_KEY_LEN:  u16 = 5;
+const NFTA_SET_ID:       u16 = 10;
+
+const NFTA_SET_ELEM_LIST_TABLE:    u16 = 1;
+const NFTA_SET_ELEM_LIST_SET:      u16 = 2;
+const NFTA_SET_ELEM_LIST_ELEMENTS: u16 = 3;
+
+const NFTA_LIST_ELEM:        u16 = 1;
+const NFTA_SET_ELEM_KEY:     u16 = 1;
+const NFTA_SET_ELEM_FLAGS:   u16 = 3;
+const NFTA_SET_ELEM_TIMEOUT: u16 = 4;
+const NFTA_DATA_VALUE:       u16 = 1;
+
+const NFT_SET_INTERVAL: u32 = 0x04;
+const NFT_SET_TIMEOUT:  u32 = 0x10;
+
+const NFT_SET_ELEM_INTERVAL_END: u32 = 0x01;
+
+const TYPE_IPV4_ADDR: u32 = 7;
+const TYPE_IPV6_ADDR: u32 = 8;
+
+pub const NFPROTO_INET: u8 = 1;
+pub const NFPROTO_IPV4: u8 = 2;
+pub const NFPROTO_IPV6: u8 = 10;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KeyType {
+    Ipv4,
+    Ipv6,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Elem {
+    pub key:     Vec<u8>,
+    pub end:     bool,
+    pub timeout: Option<u64>,
+}
+
+pub fn family(name: &str) -> Option<u8> {
+    match name {
+        "inet" => Some(NFPROTO_INET),
+        "ip"   => Some(NFPROTO_IPV4),
+        "ip6"  => Some(NFPROTO_IPV6),
+        _      => None,
+    }
+}
+
+pub fn batch(seq: u32, msgs: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = Msg::nfgen(NFNL_MSG_BATCH_BEGIN, NLM_F_REQUEST, seq, 0, NFNL_SUBSYS_NFTABLES).finish();
+    for msg in msgs {
+        buf.push_all(msg);
+    }
+    buf.push_all(&Msg::nfgen(NFNL_MSG_BATCH_END, NLM_F_REQUEST, seq, 0, NFNL_SUBSYS_NFTABLES).finish());
+    buf
+}
+
+pub fn new_table(seq: u32, family: u8, table: &str) -> Vec<u8> {
+    let flags = NLM_F_REQUEST | NLM_F_CREATE | NLM_F_ACK;
+    let mut msg = Msg::nfgen(kind(NFT_MSG_NEWTABLE), flags, seq, family, 0);
+    msg.put_str(NFTA_TABLE_NAME, table);
+    msg.finish()
+}
+
+pub fn new_set(seq: u32, family: u8, table: &str, set: &str, key: KeyType, id: u32) -> Vec<u8> {
+    let (key_type, key_len) = match key {
+        KeyType::Ipv4 => (TYPE_IPV4_ADDR, 4),
+        KeyType::Ipv6 => (TYPE_IPV6_ADDR, 16),
+    };
+
+    let flags = NLM_F_REQUEST | NLM_F_CREATE | NLM_F_ACK;
+    let mut msg = Msg::nfgen(kind(NFT_MSG_NEWSET), flags, seq, family, 0);
+    msg.put_str(NFTA_SET_TABLE, table);
+    msg.put_str(NFTA_SET_NAME, set);
+    msg.put_be32(NFTA_SET_FLAGS, NFT_SET_INTERVAL | NFT_SET_TIMEOUT);
+    msg.put_be32(NFTA_SET_KEY_TYPE, key_type);
+    msg.put_be32(NFTA_SET_KEY_LEN, key_len);
+    msg.put_be32(NFTA_SET_ID, id);
+    msg.finish()
+}
+
+pub fn new_elems(seq: u32, family: u8, table: &str, set: &str, elems: &[Elem], excl: bool) -> Vec<u8> {
+    let excl  = if excl { NLM_F_EXCL } else { 0 };
+    let flags = NLM_F_REQUEST | NLM_F_CREATE | NLM_F_ACK | excl;
+    elem_list(NFT_MSG_NEWSETELEM, flags, seq, family, table, set, elems)
+}
+
+pub fn del_elems(seq: u32, family: u8, table: &str, set: &str, elems: &[Elem]) -> Vec<u8> {
+    let flags = NLM_F_REQUEST | NLM_F_ACK;
+    elem_list(NFT_MSG_DELSETELEM, flags, seq, family, table, set, elems)
+}
+
+pub fn get_elems(seq: u32, family: u8, table: &str, set: &str) -> Vec<u8> {
+    let flags = NLM_F_REQUEST | NLM_F_DUMP;
+    let mut msg = Msg::nfgen(kind(NFT_MSG_GETSETELEM), flags, seq, family, 0);
+    msg.put_str(NFTA_SET_ELEM_LIST_TABLE, table);
+    msg.put_str(NFTA_SET_ELEM_LIST_SET, set);
+    msg.finish()
+}
+
+fn elem_list(cmd: u16, flags: u16, seq: u32, family: u8, table: &str, set: &str, elems: &[Elem]) -> Vec<u8> {
+    let mut msg = Msg::nfgen(kind(cmd), flags, seq, family, 0);
+    msg.put_str(NFTA_SET_ELEM_LIST_TABLE, table);
+    msg.put_str(NFTA_SET_ELEM_LIST_SET, set);
+    msg.begin(NFTA_SET_ELEM_LIST_ELEMENTS);
+    for elem in elems {
+        msg.begin(NFTA_LIST_ELEM);
+        msg.begin(NFTA_SET_ELEM_KEY).put(NFTA_DATA_VALUE, &elem.key).end();
+        if elem.end {
+            msg.put_be32(NFTA_SET_ELEM_FLAGS, NFT_SET_ELEM_INTERVAL_END);
+        }
+        if let Some(ms) = elem.timeout {
+            msg.put_be64(NFTA_SET_ELEM_TIMEOUT, ms);
+        }
+        msg.end();
+    }
+    msg.end();
+    msg.finish()
+}
+
+fn kind(cmd: u16) -> u16 {
+    NFNL_SUBSYS_NFTABLES << 8 | cmd
+}
+
+// An interval set stores a CIDR as its first address followed by an
+// interval end element holding the first address past the network.
+pub fn elems(cidr: Cidr, timeout: Option<u64>) -> Vec<Elem> {
+    let start = net::octets(cidr.addr());
+    let mut elems = vec![Elem { key: start.clone(), end: false, timeout: timeout }];
+    if let Some(end) = next_net(&start, cidr.len()) {
+        elems.push(Elem { key: end, end: true, timeout: None });
+    }
+    elems
+}
+
+pub fn parse_elems(payload: &[u8]) -> Result<Vec<Elem>, Error> {
+    let mut elems = Vec::new();
+    let list = match attr(try!(nfgen_attrs(payload)), NFTA_SET_ELEM_LIST_ELEMENTS) {
+        Some(list) => list,
+        None       => return Ok(elems),
+    };
+
+    for (kind, elem) in attrs(list) {
+        if kind != NFTA_LIST_ELEM {
+            continue;
+        }
+        let key = match attr(elem, NFTA_SET_ELEM_KEY).and_then(|key| attr(key, NFTA_DATA_VALUE)) {
+            Some(key) => key.to_vec(),
+            None      => continue,
+        };
+        let flags = attr(elem, NFTA_SET_ELEM_FLAGS).map(be32).unwrap_or(0);
+        elems.push(Elem {
+            key:     key,
+            end:     flags & NFT_SET_ELEM_INTERVAL_END != 0,
+            timeout: None,
+        });
+    }
+
+    Ok(elems)
+}
+
+// Rebuild CIDRs from interval elements, ignoring ranges that do not
+// fall on a prefix boundary.
+pub fn cidrs(mut elems: Vec<Elem>) -> Vec<Cidr> {
+    // an interval ending where the next starts shares its key, the end
+    // must come first
+    elems.sort_by(|a, b| (&a.key, !a.end).cmp(&(&b.key, !b.end)));
+
+    let mut cidrs = Vec::new();
+    for (n, elem) in elems.iter().enumerate() {
+        if elem.end {
+            continue;
+        }
+        let end = match elems.get(n + 1) {
+            Some(next) if next.end => Some(&next.key[..]),
+            _                      => None,
+        };
+        if let Some(len) = span_len(&elem.key, end) {
+            if let Some(addr) = net::from_octets(&elem.key) {
+                let cidr = Cidr::new(addr, len);
+                if cidr.addr() == addr {
+                    cidrs.push(cidr);
+                }
+            }
+        }
+    }
+    cidrs
+}
+
+fn next_net(start: &[u8], len: u8) -> Option<Vec<u8>> {
+    if len == 0 {
+        return None;
+    }
+
+    let mut next = start.to_vec();
+    let bit = len as usize - 1;
+    let mut carry = 1u16 << (7 - bit % 8);
+
+    for byte in next[..bit / 8 + 1].iter_mut().rev() {
+        let sum = *byte as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+
+    match carry {
+        0 => Some(next),
+        _ => None,
+    }
+}
+
+fn span_len(start: &[u8], end: Option<&[u8]>) -> Option<u8> {
+    let zero = vec![0u8; start.len()];
+    let open = end.is_none();
+    let end  = end.unwrap_or(&zero);
+
+    let mut diff   = vec![0u8; start.len()];
+    let mut borrow = 0i16;
+    for n in (0..start.len()).rev() {
+        let d = end[n] as i16 - start[n] as i16 - borrow;
+        borrow = if d < 0 { 1 } else { 0 };
+        diff[n] = (d + borrow * 256) as u8;
+    }
+
+    let bits: u32 = diff.iter().map(|b| b.count_ones()).fold(0, |a, b| a + b);
+    match bits {
+        0 if open => Some(0),
+        1         => {
+            let n = diff.iter().position(|b| *b != 0).unwrap();
+            Some((n * 8 + diff[n].leading_zeros() as usize + 1) as u8)
+        },
+        _         => None,
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use net::Cidr;
+use super::msg::*;
+
+#[test]
+#[cfg(target_endian = "little")]
+fn encode_new_elems() {
+    let cidr: Cidr = "203.0.113.0/24".parse().unwrap();
+    let elems = elems(cidr, Some(600000));
+    let msg = new_elems(1, NFPROTO_INET, "filter", "irongate_v4", &elems, true);
+
+    assert_eq!(vec![
+        0x68, 0x00, 0x00, 0x00, 0x0c, 0x0a, 0x05, 0x06, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x01, 0x00, 0x66, 0x69, 0x6c, 0x74, 0x65, 0x72, 0x00, 0x00,
+        0x10, 0x00, 0x02, 0x00, 0x69, 0x72, 0x6f, 0x6e, 0x67, 0x61, 0x74, 0x65, 0x5f, 0x76, 0x34, 0x00,
+        0x38, 0x00, 0x03, 0x80, 0x1c, 0x00, 0x01, 0x80, 0x0c, 0x00, 0x01, 0x80, 0x08, 0x00, 0x01, 0x00,
+        0xcb, 0x00, 0x71, 0x00, 0x0c, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09, 0x27, 0xc0,
+        0x18, 0x00, 0x01, 0x80, 0x0c, 0x00, 0x01, 0x80, 0x08, 0x00, 0x01, 0x00, 0xcb, 0x00, 0x72, 0x00,
+        0x08, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01,
+    ], msg);
+}
+
+#[test]
+#[cfg(target_endian = "little")]
+fn encode_del_elems_batch() {
+    let cidr: Cidr = "2001:db8::1".parse().unwrap();
+    let msg = del_elems(3, NFPROTO_INET, "t", "s_v6", &elems(cidr, None));
+
+    assert_eq!(vec![
+        0x14, 0x00, 0x00, 0x00, 0x10, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x0a, 0x6c, 0x00, 0x00, 0x00, 0x0e, 0x0a, 0x05, 0x00, 0x03, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x74, 0x00, 0x00, 0x00,
+        0x09, 0x00, 0x02, 0x00, 0x73, 0x5f, 0x76, 0x36, 0x00, 0x00, 0x00, 0x00, 0x44, 0x00, 0x03, 0x80,
+        0x1c, 0x00, 0x01, 0x80, 0x18, 0x00, 0x01, 0x80, 0x14, 0x00, 0x01, 0x00, 0x20, 0x01, 0x0d, 0xb8,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x24, 0x00, 0x01, 0x80,
+        0x18, 0x00, 0x01, 0x80, 0x14, 0x00, 0x01, 0x00, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x08, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x14, 0x00, 0x00, 0x00, 0x11, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x0a,
+    ], batch(2, &[msg]));
+}
+
+#[test]
+fn interval_elems() {
+    let net: Cidr = "203.0.113.0/24".parse().unwrap();
+    assert_eq!(vec![
+        Elem { key: vec![203, 0, 113, 0], end: false, timeout: Some(1000) },
+        Elem { key: vec![203, 0, 114, 0], end: true,  timeout: None },
+    ], elems(net, Some(1000)));
+
+    let top: Cidr = "255.255.255.0/24".parse().unwrap();
+    assert_eq!(1, elems(top, None).len());
+
+    let carry: Cidr = "10.0.255.255".parse().unwrap();
+    assert_eq!(vec![10, 1, 0, 0], elems(carry, None)[1].key);
+}
+
+#[test]
+fn interval_cidrs() {
+    let cidrs_in: Vec<Cidr> = vec![
+        "198.51.100.7".parse().unwrap(),
+        "203.0.113.0/24".parse().unwrap(),
+        "255.255.255.0/24".parse().unwrap(),
+    ];
+
+    let mut all = Vec::new();
+    for cidr in cidrs_in.iter().rev() {
+        all.extend(elems(*cidr, None).into_iter());
+    }
+    all.push(Elem { key: vec![0, 0, 0, 0], end: true, timeout: None });
+
+    assert_eq!(cidrs_in, cidrs(all));
+}
+
+#[test]
+fn parse_dump() {
+    let cidr: Cidr = "2001:db8::/48".parse().unwrap();
+    let msg = new_elems(1, NFPROTO_INET, "filter", "irongate_v6", &elems(cidr, Some(5000)), false);
+    let payload = &msg[16..];
+
+    let parsed = parse_elems(payload).unwrap();
+    assert_eq!(2, parsed.len());
+    assert_eq!(vec![cidr], cidrs(parsed));
+    assert!(parse_elems(&payload[..3]).is_err());
+}
+
+#[test]
+fn adjacent_hosts() {
+    let a: Cidr = "192.0.2.4".parse().unwrap();
+    let b: Cidr = "192.0.2.5".parse().unwrap();
+
+    // the end of a and the start of b share the key 192.0.2.5
+    let mut all = elems(b, None);
+    all.extend(elems(a, None).into_iter());
+    all.swap(0, 1);
+    assert_eq!(vec![a, b], cidrs(all));
+}
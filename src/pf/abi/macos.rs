@@ -0,0 +1,88 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+// macOS, where pf_rule_addr holds a port/call id/spi union and the
+// number of states killed is returned in psk_af.
+
+use libc::types::common::c99::{int64_t, uint8_t, uint16_t, uint32_t, uint64_t};
+use libc::types::os::arch::c95::{c_char, c_int, c_ulong};
+use super::Table;
+
+pub const STATE_KILL_LEN: c_ulong = 216;
+pub const DIOCKILLSTATES: c_ulong = iowr!(41, STATE_KILL_LEN);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Addr {
+    pub addr:  [u8; 16],
+    pub af:    uint8_t,
+    pub net:   uint8_t,
+    pub not:   uint8_t,
+    pub fback: uint8_t,
+}
+
+// struct pfr_astats, with counters indexed by direction (in, out) and
+// then action (block, pass).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AStats {
+    pub addr:    Addr,
+    pub packets: [[uint64_t; 2]; 2],
+    pub bytes:   [[uint64_t; 2]; 2],
+    pub tzero:   int64_t,
+}
+
+// struct pfr_tstats, with a third action counting packets that matched
+// the table lookup but no entry.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TStats {
+    pub table:   Table,
+    pub packets: [[uint64_t; 3]; 2],
+    pub bytes:   [[uint64_t; 3]; 2],
+    pub matched: uint64_t,
+    pub nomatch: uint64_t,
+    pub tzero:   int64_t,
+    pub cnt:     c_int,
+    pub refcnt:  [c_int; 2],
+}
+
+#[repr(C)]
+pub struct StateKill {
+    pub af:            uint8_t,
+    pub proto:         uint8_t,
+    pub proto_variant: uint8_t,
+    pub pad:           uint8_t,
+    pub src:           RuleAddr,
+    pub dst:           RuleAddr,
+    pub ifname:        [c_char; 16],
+    pub ownername:     [c_char; 64],
+}
+
+#[repr(C)]
+pub struct RuleAddr {
+    pub addr:  AddrWrap,
+    pub xport: Xport,
+    pub neg:   uint8_t,
+}
+
+// union pf_rule_xport { struct pf_port_range; u_int16_t call_id; u_int32_t spi; }
+#[repr(C)]
+pub struct Xport {
+    pub spi:  uint32_t,
+    pub rest: [uint16_t; 2],
+}
+
+#[repr(C)]
+pub struct AddrWrap {
+    pub addr:   [u8; 16],
+    pub mask:   [u8; 16],
+    pub p:      uint64_t,
+    pub kind:   uint8_t,
+    pub iflags: uint8_t,
+}
+
+impl StateKill {
+    pub fn killed(&self) -> usize {
+        self.af as usize
+    }
+}
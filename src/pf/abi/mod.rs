@@ -0,0 +1,73 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+// Kernel structures and ioctl requests for each pf flavor. All of them
+// are compiled on every host so the layout tests run anywhere, and the
+// one matching the target OS is re-exported. Layouts assume an LP64 ABI.
+
+use libc::types::common::c95::c_void;
+use libc::types::common::c99::{int8_t, int32_t, uint32_t};
+use libc::types::os::arch::c95::{c_char, c_int, c_ulong};
+
+// _IOWR('D', num, len) as encoded by <sys/ioccom.h> on every BSD.
+macro_rules! iowr {
+    ($num:expr, $len:expr) => {
+        0xC0000000 | (($len as c_ulong & 0x1fff) << 16) | ((b'D' as c_ulong) << 8) | $num
+    }
+}
+
+pub mod freebsd;
+pub mod macos;
+pub mod netbsd;
+pub mod openbsd;
+
+#[cfg(target_os = "macos")]   pub use self::macos::*;
+#[cfg(target_os = "netbsd")]  pub use self::netbsd::*;
+#[cfg(target_os = "openbsd")] pub use self::openbsd::*;
+#[cfg(not(any(target_os = "macos", target_os = "netbsd", target_os = "openbsd")))]
+pub use self::freebsd::*;
+
+// struct pfr_table and struct pfioc_table are the same everywhere.
+pub const PFIOC_TABLE_LEN: c_ulong = 1104;
+
+pub const DIOCRADDTABLES: c_ulong = iowr!(61, PFIOC_TABLE_LEN);
+pub const DIOCRGETTABLES: c_ulong = iowr!(63, PFIOC_TABLE_LEN);
+pub const DIOCRGETTSTATS: c_ulong = iowr!(64, PFIOC_TABLE_LEN);
+pub const DIOCRCLRADDRS:  c_ulong = iowr!(66, PFIOC_TABLE_LEN);
+pub const DIOCRADDADDRS:  c_ulong = iowr!(67, PFIOC_TABLE_LEN);
+pub const DIOCRDELADDRS:  c_ulong = iowr!(68, PFIOC_TABLE_LEN);
+pub const DIOCRSETADDRS:  c_ulong = iowr!(69, PFIOC_TABLE_LEN);
+pub const DIOCRGETADDRS:  c_ulong = iowr!(70, PFIOC_TABLE_LEN);
+pub const DIOCRGETASTATS: c_ulong = iowr!(71, PFIOC_TABLE_LEN);
+
+pub const PFR_FLAG_FEEDBACK: c_int = 0x04;
+pub const PFR_FB_ADDED:      u8    = 2;
+
+#[repr(C)]
+#[derive(Copy)]
+pub struct Table {
+    pub anchor: [c_char; 1024],
+    pub name:   [c_char; 32],
+    pub flags:  int32_t,
+    pub fback:  int8_t,
+}
+
+#[repr(C)]
+pub struct Command {
+    pub table:   Table,
+    pub buffer:  *mut c_void,
+    pub esize:   c_int,
+    pub size:    c_int,
+    pub size2:   c_int,
+    pub nadd:    c_int,
+    pub ndel:    c_int,
+    pub nchange: c_int,
+    pub flags:   c_int,
+    pub ticket:  uint32_t,
+}
+
+pub fn ioctl_len(request: c_ulong) -> usize {
+    (request >> 16 & 0x1fff) as usize
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,93 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+// OpenBSD, whose pfr_addr grew interface, state count and weight fields
+// for route-to tables, whose pf_rule_addr carries a weight, and whose
+// table counters have an extra match action.
+
+use libc::types::common::c95::c_void;
+use libc::types::common::c99::{int64_t, uint8_t, uint16_t, uint32_t, uint64_t};
+use libc::types::os::arch::c95::{c_char, c_int, c_uint, c_ulong};
+use super::Table;
+
+pub const STATE_KILL_LEN: c_ulong = 224;
+pub const DIOCKILLSTATES: c_ulong = iowr!(41, STATE_KILL_LEN);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Addr {
+    pub addr:   [u8; 16],
+    pub ifname: [c_char; 16],
+    pub states: uint32_t,
+    pub weight: uint16_t,
+    pub af:     uint8_t,
+    pub net:    uint8_t,
+    pub not:    uint8_t,
+    pub fback:  uint8_t,
+    pub kind:   uint8_t,
+    pub pad:    [uint8_t; 7],
+}
+
+// struct pfr_astats, whose actions are block, match and pass.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AStats {
+    pub addr:    Addr,
+    pub packets: [[uint64_t; 3]; 2],
+    pub bytes:   [[uint64_t; 3]; 2],
+    pub tzero:   int64_t,
+}
+
+// struct pfr_tstats, with a fourth action counting packets that matched
+// the table lookup but no entry.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TStats {
+    pub table:   Table,
+    pub packets: [[uint64_t; 4]; 2],
+    pub bytes:   [[uint64_t; 4]; 2],
+    pub matched: uint64_t,
+    pub nomatch: uint64_t,
+    pub tzero:   int64_t,
+    pub cnt:     c_int,
+    pub refcnt:  [c_int; 2],
+}
+
+#[repr(C)]
+pub struct StateKill {
+    pub id:        uint64_t,
+    pub creatorid: uint32_t,
+    pub direction: uint8_t,
+    pub pad:       [uint8_t; 3],
+    pub af:        uint8_t,
+    pub proto:     c_int,
+    pub src:       RuleAddr,
+    pub dst:       RuleAddr,
+    pub ifname:    [c_char; 16],
+    pub label:     [c_char; 64],
+    pub killed:    c_uint,
+    pub rdomain:   uint16_t,
+}
+
+#[repr(C)]
+pub struct RuleAddr {
+    pub addr:    AddrWrap,
+    pub port:    [uint16_t; 2],
+    pub neg:     uint8_t,
+    pub port_op: uint8_t,
+    pub weight:  uint16_t,
+}
+
+#[repr(C)]
+pub struct AddrWrap {
+    pub addr:   [u8; 16],
+    pub mask:   [u8; 16],
+    pub p:      *mut c_void,
+    pub kind:   uint8_t,
+    pub iflags: uint8_t,
+}
+
+impl StateKill {
+    pub fn killed(&self) -> usize {
+        self.killed as usize
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::mem;
+use libc::types::os::arch::c95::c_ulong;
+use super::*;
+
+macro_rules! offset {
+    ($t:ty, $($field:ident).+) => {{
+        let v: $t = unsafe { mem::zeroed() };
+        &v.$($field).+ as *const _ as usize - &v as *const _ as usize
+    }}
+}
+
+#[test]
+fn table_layout() {
+    assert_eq!(1064, mem::size_of::<Table>());
+    assert_eq!(1024, offset!(Table, name));
+    assert_eq!(1056, offset!(Table, flags));
+    assert_eq!(1060, offset!(Table, fback));
+}
+
+#[test]
+fn command_layout() {
+    assert_eq!(PFIOC_TABLE_LEN as usize, mem::size_of::<Command>());
+    assert_eq!(1064, offset!(Command, buffer));
+    assert_eq!(1072, offset!(Command, esize));
+    assert_eq!(1084, offset!(Command, nadd));
+    assert_eq!(1100, offset!(Command, ticket));
+}
+
+#[test]
+fn table_ioctls() {
+    assert_eq!(0xC450443D, DIOCRADDTABLES);
+    assert_eq!(0xC450443F, DIOCRGETTABLES);
+    assert_eq!(0xC4504440, DIOCRGETTSTATS);
+    assert_eq!(0xC4504442, DIOCRCLRADDRS);
+    assert_eq!(0xC4504443, DIOCRADDADDRS);
+    assert_eq!(0xC4504444, DIOCRDELADDRS);
+    assert_eq!(0xC4504445, DIOCRSETADDRS);
+    assert_eq!(0xC4504446, DIOCRGETADDRS);
+    assert_eq!(0xC4504447, DIOCRGETASTATS);
+    assert_eq!(mem::size_of::<Command>(), ioctl_len(DIOCRGETADDRS));
+}
+
+#[test]
+fn tstats_layout() {
+    use super::freebsd::TStats;
+
+    assert_eq!(1200, mem::size_of::<TStats>());
+    assert_eq!(1064, offset!(TStats, packets));
+    assert_eq!(1112, offset!(TStats, bytes));
+    assert_eq!(1160, offset!(TStats, matched));
+    assert_eq!(1176, offset!(TStats, tzero));
+    assert_eq!(1184, offset!(TStats, cnt));
+    assert_eq!(1188, offset!(TStats, refcnt));
+    assert_eq!(1200, mem::size_of::<super::netbsd::TStats>());
+    assert_eq!(1200, mem::size_of::<super::macos::TStats>());
+}
+
+#[test]
+fn astats_layout() {
+    use super::freebsd::AStats;
+
+    assert_eq!(96, mem::size_of::<AStats>());
+    assert_eq!(24, offset!(AStats, packets));
+    assert_eq!(56, offset!(AStats, bytes));
+    assert_eq!(88, offset!(AStats, tzero));
+    assert_eq!(96, mem::size_of::<super::netbsd::AStats>());
+    assert_eq!(96, mem::size_of::<super::macos::AStats>());
+}
+
+#[test]
+fn freebsd_layout() {
+    use super::freebsd::*;
+
+    assert_eq!(20, mem::size_of::<Addr>());
+    assert_eq!(16, offset!(Addr, af));
+
+    assert_eq!(48, mem::size_of::<AddrWrap>());
+    assert_eq!(56, mem::size_of::<RuleAddr>());
+    assert_eq!(STATE_KILL_LEN as usize, mem::size_of::<StateKill>());
+    assert_eq!(16,  offset!(StateKill, af));
+    assert_eq!(24,  offset!(StateKill, src));
+    assert_eq!(72,  offset!(StateKill, src.port));
+    assert_eq!(80,  offset!(StateKill, dst));
+    assert_eq!(216, offset!(StateKill, killed));
+    assert_eq!(0xC0E04429, DIOCKILLSTATES);
+}
+
+#[test]
+fn openbsd_layout() {
+    use super::openbsd::*;
+
+    assert_eq!(52, mem::size_of::<Addr>());
+    assert_eq!(16, offset!(Addr, ifname));
+    assert_eq!(32, offset!(Addr, states));
+    assert_eq!(36, offset!(Addr, weight));
+    assert_eq!(38, offset!(Addr, af));
+    assert_eq!(42, offset!(Addr, kind));
+
+    assert_eq!(160, mem::size_of::<AStats>());
+    assert_eq!(56,  offset!(AStats, packets));
+    assert_eq!(104, offset!(AStats, bytes));
+    assert_eq!(152, offset!(AStats, tzero));
+
+    assert_eq!(1232, mem::size_of::<TStats>());
+    assert_eq!(1064, offset!(TStats, packets));
+    assert_eq!(1128, offset!(TStats, bytes));
+    assert_eq!(1192, offset!(TStats, matched));
+    assert_eq!(1208, offset!(TStats, tzero));
+    assert_eq!(1216, offset!(TStats, cnt));
+    assert_eq!(1220, offset!(TStats, refcnt));
+
+    assert_eq!(56, mem::size_of::<RuleAddr>());
+    assert_eq!(54, offset!(RuleAddr, weight));
+    assert_eq!(STATE_KILL_LEN as usize, mem::size_of::<StateKill>());
+    assert_eq!(216, offset!(StateKill, killed));
+    assert_eq!(220, offset!(StateKill, rdomain));
+    assert_eq!(0xC0E04429, DIOCKILLSTATES);
+}
+
+#[test]
+fn netbsd_layout() {
+    use super::netbsd::*;
+
+    assert_eq!(20, mem::size_of::<Addr>());
+    assert_eq!(STATE_KILL_LEN as usize, mem::size_of::<StateKill>());
+    assert_eq!(4,   offset!(StateKill, proto));
+    assert_eq!(8,   offset!(StateKill, src));
+    assert_eq!(64,  offset!(StateKill, dst));
+    assert_eq!(120, offset!(StateKill, ifname));
+    assert_eq!(0xC0884429, DIOCKILLSTATES);
+}
+
+#[test]
+fn macos_layout() {
+    use super::macos::*;
+
+    assert_eq!(20, mem::size_of::<Addr>());
+    assert_eq!(8,  mem::size_of::<Xport>());
+    assert_eq!(64, mem::size_of::<RuleAddr>());
+    assert_eq!(48, offset!(RuleAddr, xport));
+    assert_eq!(56, offset!(RuleAddr, neg));
+    assert_eq!(STATE_KILL_LEN as usize, mem::size_of::<StateKill>());
+    assert_eq!(8,   offset!(StateKill, src));
+    assert_eq!(72,  offset!(StateKill, dst));
+    assert_eq!(136, offset!(StateKill, ifname));
+    assert_eq!(152, offset!(StateKill, ownername));
+    assert_eq!(0xC0D84429, DIOCKILLSTATES);
+}
+
+#[test]
+fn ioctl_encoding() {
+    let requests: [(c_ulong, usize); 4] = [
+        (super::freebsd::DIOCKILLSTATES, mem::size_of::<super::freebsd::StateKill>()),
+        (super::openbsd::DIOCKILLSTATES, mem::size_of::<super::openbsd::StateKill>()),
+        (super::netbsd::DIOCKILLSTATES,  mem::size_of::<super::netbsd::StateKill>()),
+        (super::macos::DIOCKILLSTATES,   mem::size_of::<super::macos::StateKill>()),
+    ];
+    for &(request, len) in &requests {
+        assert_eq!(len, ioctl_len(request));
+    }
+}
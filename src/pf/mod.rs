@@ -0,0 +1,474 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::ffi::CStr;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::intrinsics::{bswap16, copy};
+use std::io::{Error, ErrorKind};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::str::{self, FromStr};
+
+use libc::consts::os::bsd44::{AF_INET, AF_INET6};
+use libc::consts::os::posix88::ESRCH;
+use block::{Blocker, Changes, Stats};
+use chrono::{DateTime, Duration, TimeZone, UTC};
+use net::{Cidr, CidrError};
+use libc::funcs::bsd44::ioctl;
+use libc::types::common::c95::c_void;
+use libc::types::common::c99::{int32_t, uint8_t};
+use libc::types::os::arch::c95::{c_char, c_int, c_ulong};
+
+mod abi;
+
+pub use self::abi::{Addr, AStats, Table, TStats};
+use self::abi::{Command, StateKill, PFR_FLAG_FEEDBACK, PFR_FB_ADDED};
+use self::abi::{DIOCRADDTABLES, DIOCRGETTABLES, DIOCRGETTSTATS, DIOCRGETASTATS, DIOCKILLSTATES};
+use self::abi::{DIOCRCLRADDRS, DIOCRADDADDRS, DIOCRDELADDRS, DIOCRSETADDRS, DIOCRGETADDRS};
+
+bitflags! {
+    flags TableFlags: int32_t {
+        const PFR_TFLAG_PERSIST  = 0x01,
+        const PFR_TFLAG_CONST    = 0x02,
+        const PFR_TFLAG_COUNTERS = 0x40,
+    }
+}
+
+pub struct Pf {
+    file:   File,
+    anchor: Option<String>,
+    flags:  TableFlags,
+}
+
+impl Pf {
+    pub fn new() -> Result<Self, Error> {
+        Pf::with_anchor(None)
+    }
+
+    pub fn with_anchor(anchor: Option<&str>) -> Result<Self, Error> {
+        match OpenOptions::new().read(true).write(true).open("/dev/pf") {
+            Ok(file) => Ok(Pf {
+                file:   file,
+                anchor: anchor.map(|a| a.to_string()),
+                flags:  PFR_TFLAG_PERSIST,
+            }),
+            Err(err) => Err(Error::new(err.kind(), "failed to open /dev/pf")),
+        }
+    }
+
+    pub fn set_table_flags(&mut self, flags: TableFlags) {
+        self.flags = flags;
+    }
+
+    pub fn anchor(&self) -> Option<&str> {
+        self.anchor.as_ref().map(|a| &a[..])
+    }
+
+    pub fn tables(&self) -> Result<Vec<Table>, Error> {
+        self.get(DIOCRGETTABLES, &mut try!(Command::with_anchor(self.anchor())))
+    }
+
+    pub fn add_tables(&self, tables: &[Table]) -> Result<isize, Error> {
+        let mut cmd = Command::new();
+        match self.modify(DIOCRADDTABLES, &mut cmd, tables) {
+            Ok(()) => Ok(cmd.nadd as isize),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn addrs(&self, table: &str) -> Result<Vec<Addr>, Error> {
+        self.get(DIOCRGETADDRS, &mut try!(Command::with_table(table, self.anchor())))
+    }
+
+    pub fn table_stats(&self) -> Result<Vec<TStats>, Error> {
+        self.get(DIOCRGETTSTATS, &mut try!(Command::with_anchor(self.anchor())))
+    }
+
+    pub fn addr_stats(&self, table: &str) -> Result<Vec<AStats>, Error> {
+        self.get(DIOCRGETASTATS, &mut try!(Command::with_table(table, self.anchor())))
+    }
+
+    pub fn add_addrs(&self, table: &str, addrs: &[Addr]) -> Result<isize, Error> {
+        let mut cmd = try!(Command::with_table(table, self.anchor()));
+        match self.modify(DIOCRADDADDRS, &mut cmd, addrs) {
+            Ok(()) => Ok(cmd.nadd as isize),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Add addresses, with the kernel marking each entry it added.
+    pub fn add_addrs_feedback(&self, table: &str, addrs: &mut [Addr]) -> Result<isize, Error> {
+        let mut cmd = try!(Command::with_table(table, self.anchor()));
+        unsafe {
+            cmd.flags  = PFR_FLAG_FEEDBACK;
+            cmd.esize  = mem::size_of::<Addr>() as c_int;
+            cmd.buffer = addrs.as_mut_ptr() as *mut c_void;
+            cmd.size   = addrs.len() as c_int;
+            try!(self.file.ioctl(DIOCRADDADDRS, &mut cmd as *mut _ as *mut c_void));
+        }
+        Ok(cmd.nadd as isize)
+    }
+
+    pub fn del_addrs(&self, table: &str, addrs: &[Addr]) -> Result<isize, Error> {
+        let mut cmd = try!(Command::with_table(table, self.anchor()));
+        match self.modify(DIOCRDELADDRS, &mut cmd, addrs) {
+            Ok(()) => Ok(cmd.ndel as isize),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_addrs(&self, table: &str, addrs: &[Addr]) -> Result<Changes, Error> {
+        let mut cmd = try!(Command::with_table(table, self.anchor()));
+        try!(self.modify(DIOCRSETADDRS, &mut cmd, addrs));
+        Ok(Changes {
+            added:   cmd.nadd as usize,
+            removed: cmd.ndel as usize,
+            changed: cmd.nchange as usize,
+        })
+    }
+
+    pub fn clear(&self, table: &str) -> Result<usize, Error> {
+        let mut cmd = try!(Command::with_table(table, self.anchor()));
+        unsafe {
+            try!(self.file.ioctl(DIOCRCLRADDRS, &mut cmd as *mut _ as *mut c_void));
+        }
+        Ok(cmd.ndel as usize)
+    }
+
+    pub fn kill_states_from(&self, src: Cidr) -> Result<usize, Error> {
+        let mut kill = StateKill::new(src);
+        unsafe {
+            try!(self.file.ioctl(DIOCKILLSTATES, &mut kill as *mut _ as *mut c_void));
+        }
+        Ok(kill.killed())
+    }
+
+    fn get<T: Copy>(&self, request: c_ulong, cmd: &mut Command) -> Result<Vec<T>, Error> {
+        get(&self.file, request, cmd)
+    }
+
+    fn modify<T>(&self, request: c_ulong, cmd: &mut Command, slice: &[T]) -> Result<(), Error> {
+        unsafe {
+            cmd.esize  = mem::size_of::<T>() as c_int;
+            cmd.buffer = slice.as_ptr() as *mut c_void;
+            cmd.size   = slice.len() as c_int;
+            self.file.ioctl(request, cmd as *mut _ as *mut c_void)
+        }
+    }
+}
+
+pub trait Device {
+    unsafe fn ioctl(&self, request: c_ulong, arg: *mut c_void) -> Result<(), Error>;
+}
+
+impl Device for File {
+    unsafe fn ioctl(&self, request: c_ulong, arg: *mut c_void) -> Result<(), Error> {
+        match ioctl(self.as_raw_fd(), request, arg) {
+            -1 => Err(ioctl_error(Error::last_os_error())),
+             _ => Ok(()),
+        }
+    }
+}
+
+// pf reports a missing table or anchor as ESRCH.
+fn ioctl_error(err: Error) -> Error {
+    match err.raw_os_error() {
+        Some(ESRCH) => Error::new(ErrorKind::NotFound, "table does not exist"),
+        _           => err,
+    }
+}
+
+const GET_ATTEMPTS: usize = 8;
+
+// Fetch a table listing. The kernel copies nothing and reports the size
+// it needs when the buffer is too small, which happens whenever the table
+// grows between asking for its size and fetching it, so retry with the
+// new size. The buffer is zeroed and truncated to the number of entries
+// the kernel actually wrote.
+fn get<T: Copy, D: Device>(dev: &D, request: c_ulong, cmd: &mut Command) -> Result<Vec<T>, Error> {
+    unsafe {
+        cmd.esize  = mem::size_of::<T>() as c_int;
+        cmd.buffer = ptr::null_mut();
+        cmd.size   = 0;
+        try!(dev.ioctl(request, cmd as *mut _ as *mut c_void));
+
+        for _ in 0..GET_ATTEMPTS {
+            let capacity = cmd.size as usize;
+            let mut vec: Vec<T> = vec![mem::zeroed(); capacity];
+            cmd.buffer = vec.as_mut_ptr() as *mut c_void;
+            try!(dev.ioctl(request, cmd as *mut _ as *mut c_void));
+
+            if cmd.size as usize <= capacity {
+                vec.truncate(cmd.size as usize);
+                return Ok(vec);
+            }
+        }
+    }
+    Err(Error::new(ErrorKind::Interrupted, "table changed during listing"))
+}
+
+impl Blocker for Pf {
+    fn ensure_table(&mut self, table: &str) -> Result<(), Error> {
+        self.add_tables(&[try!(Table::new(table, self.anchor())).with_flags(self.flags)]).map(|_| ())
+    }
+
+    fn add(&mut self, table: &str, addrs: &[Cidr], _: Option<Duration>) -> Result<usize, Error> {
+        let addrs: Vec<Addr> = addrs.iter().map(|cidr| Addr::from_cidr(*cidr)).collect();
+        self.add_addrs(table, &addrs).map(|n| n as usize)
+    }
+
+    fn add_each(&mut self, table: &str, addrs: &[Cidr], _: Option<Duration>) -> Result<Vec<bool>, Error> {
+        let mut addrs: Vec<Addr> = addrs.iter().map(|cidr| Addr::from_cidr(*cidr)).collect();
+        try!(self.add_addrs_feedback(table, &mut addrs));
+        Ok(addrs.iter().map(|addr| addr.is_added()).collect())
+    }
+
+    fn remove(&mut self, table: &str, addrs: &[Cidr]) -> Result<usize, Error> {
+        let addrs: Vec<Addr> = addrs.iter().map(|cidr| Addr::from_cidr(*cidr)).collect();
+        self.del_addrs(table, &addrs).map(|n| n as usize)
+    }
+
+    fn list(&mut self, table: &str) -> Result<Vec<Cidr>, Error> {
+        let addrs = try!(self.addrs(table));
+        Ok(addrs.iter().filter(|addr| !addr.is_negated()).map(|addr| addr.as_cidr()).collect())
+    }
+
+    fn replace(&mut self, table: &str, addrs: &[(Cidr, Option<Duration>)]) -> Result<Changes, Error> {
+        let addrs: Vec<Addr> = addrs.iter().map(|&(cidr, _)| Addr::from_cidr(cidr)).collect();
+        match addrs.is_empty() {
+            true  => self.clear(table).map(|n| Changes { removed: n, ..Changes::default() }),
+            false => self.set_addrs(table, &addrs),
+        }
+    }
+
+    fn stats(&mut self, table: &str) -> Result<Vec<Stats>, Error> {
+        let stats = try!(self.addr_stats(table));
+        Ok(stats.iter().filter(|stats| !stats.addr.is_negated()).map(|stats| Stats {
+            cidr:    stats.addr.as_cidr(),
+            packets: stats.blocked_packets(),
+            bytes:   stats.blocked_bytes(),
+            added:   Some(stats.added()),
+        }).collect())
+    }
+
+    fn kill_states(&mut self, addrs: &[Cidr]) -> Result<usize, Error> {
+        let mut n = 0;
+        for cidr in addrs {
+            n += try!(self.kill_states_from(*cidr));
+        }
+        Ok(n)
+    }
+}
+
+impl<'a> Table {
+    pub fn new(name: &str, anchor: Option<&str>) -> Result<Self, Error> {
+        let mut table: Self = unsafe { mem::zeroed() };
+        try!(copy_str(&mut table.name, name, "table name"));
+        try!(copy_str(&mut table.anchor, anchor.unwrap_or(""), "anchor"));
+        Ok(table)
+    }
+
+    pub fn with_flags(mut self, flags: TableFlags) -> Self {
+        self.flags = flags.bits();
+        self
+    }
+
+    pub fn flags(&self) -> TableFlags {
+        TableFlags::from_bits_truncate(self.flags)
+    }
+
+    pub fn name(&self) -> &'a str {
+        unsafe {
+            let name = CStr::from_ptr(self.name.as_ptr());
+            str::from_utf8(name.to_bytes()).unwrap()
+        }
+    }
+
+    pub fn anchor(&self) -> &'a str {
+        unsafe {
+            let anchor = CStr::from_ptr(self.anchor.as_ptr());
+            str::from_utf8(anchor.to_bytes()).unwrap()
+        }
+    }
+}
+
+impl Clone for Table { fn clone(&self) -> Table { *self } }
+
+impl Addr {
+    pub fn new(ip: IpAddr) -> Self {
+        Addr::from_cidr(Cidr::host(ip))
+    }
+
+    pub fn from_cidr(cidr: Cidr) -> Self {
+        unsafe {
+            let mut addr: Self = mem::zeroed();
+            match cidr.addr() {
+                IpAddr::V4(v4) => {
+                    addr.af  = AF_INET as uint8_t;
+                    copy(v4.octets().as_ptr(), addr.addr.as_mut_ptr(), 4);
+                },
+                IpAddr::V6(v6) => {
+                    addr.af  = AF_INET6 as uint8_t;
+                    addr.addr = mem::transmute(Addr::bswap(v6.segments()));
+                },
+            }
+            addr.net = cidr.len() as uint8_t;
+            addr
+        }
+    }
+
+    pub fn negate(mut self) -> Self {
+        self.not = (self.not == 0) as uint8_t;
+        self
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.not != 0
+    }
+
+    pub fn is_added(&self) -> bool {
+        self.fback == PFR_FB_ADDED
+    }
+
+    pub fn as_cidr(&self) -> Cidr {
+        Cidr::new(self.as_ip_addr(), self.net as u8)
+    }
+
+    pub fn as_ip_addr(&self) -> IpAddr {
+        match self.af as i32 {
+            AF_INET => {
+                let w = &self.addr;
+                IpAddr::V4(Ipv4Addr::new(w[0], w[1], w[2], w[3]))
+            },
+            AF_INET6 => unsafe {
+                let w = Addr::bswap(mem::transmute_copy(&self.addr));
+                IpAddr::V6(Ipv6Addr::new(w[0], w[1], w[2], w[3], w[4], w[5], w[6], w[7]))
+            },
+            _ => panic!("Unknown address family"),
+        }
+    }
+
+    #[cfg(target_endian = "little")]
+    unsafe fn bswap(mut words: [u16; 8]) -> [u16; 8] {
+        for word in words.iter_mut() {
+            *word = bswap16(*word);
+        }
+        words
+    }
+}
+
+const IN:    usize = 0;
+const OUT:   usize = 1;
+const BLOCK: usize = 0;
+
+impl AStats {
+    pub fn addr(&self) -> Addr {
+        self.addr
+    }
+
+    pub fn blocked_packets(&self) -> u64 {
+        self.packets[IN][BLOCK] + self.packets[OUT][BLOCK]
+    }
+
+    pub fn blocked_bytes(&self) -> u64 {
+        self.bytes[IN][BLOCK] + self.bytes[OUT][BLOCK]
+    }
+
+    pub fn added(&self) -> DateTime<UTC> {
+        UTC.timestamp(self.tzero, 0)
+    }
+}
+
+impl TStats {
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn len(&self) -> usize {
+        self.cnt as usize
+    }
+
+    pub fn blocked_packets(&self) -> u64 {
+        self.packets[IN][BLOCK] + self.packets[OUT][BLOCK]
+    }
+
+    pub fn blocked_bytes(&self) -> u64 {
+        self.bytes[IN][BLOCK] + self.bytes[OUT][BLOCK]
+    }
+
+    pub fn since(&self) -> DateTime<UTC> {
+        UTC.timestamp(self.tzero, 0)
+    }
+}
+
+impl FromStr for Addr {
+    type Err = CidrError;
+
+    fn from_str(s: &str) -> Result<Addr, CidrError> {
+        match s.starts_with("!") {
+            true  => Ok(Addr::from_cidr(try!(s[1..].parse())).negate()),
+            false => Ok(Addr::from_cidr(try!(s.parse()))),
+        }
+    }
+}
+
+impl Display for Addr {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        if self.is_negated() {
+            try!(fmt.write_str("!"));
+        }
+        write!(fmt, "{}", self.as_cidr())
+    }
+}
+
+impl StateKill {
+    fn new(src: Cidr) -> Self {
+        unsafe {
+            let mut kill: Self = mem::zeroed();
+            let addr = Addr::from_cidr(src);
+            kill.af            = addr.af;
+            kill.src.addr.addr = addr.addr;
+            for n in 0..src.len() as usize {
+                kill.src.addr.mask[n / 8] |= 0x80 >> (n % 8);
+            }
+            kill
+        }
+    }
+}
+
+impl Command {
+    fn new() -> Self {
+        unsafe { mem::zeroed() }
+    }
+
+    fn with_anchor(anchor: Option<&str>) -> Result<Self, Error> {
+        let mut cmd = Command::new();
+        try!(copy_str(&mut cmd.table.anchor, anchor.unwrap_or(""), "anchor"));
+        Ok(cmd)
+    }
+
+    fn with_table(table: &str, anchor: Option<&str>) -> Result<Self, Error> {
+        let mut cmd = Command::new();
+        cmd.table = try!(Table::new(table, anchor));
+        Ok(cmd)
+    }
+}
+
+// Copy a string into a fixed size, NUL terminated C buffer, refusing
+// strings that don't fit rather than naming some other table. The
+// buffer must already be zeroed.
+fn copy_str(dst: &mut [c_char], s: &str, what: &str) -> Result<(), Error> {
+    if s.len() >= dst.len() {
+        let msg = format!("{} '{}' is longer than {} bytes", what, s, dst.len() - 1);
+        return Err(Error::new(ErrorKind::InvalidInput, msg));
+    }
+    unsafe {
+        copy(mem::transmute(s.as_ptr()), dst.as_mut_ptr(), s.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
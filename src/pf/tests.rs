@@ -0,0 +1,191 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::cell::RefCell;
+use std::mem;
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr};
+use libc::consts::os::bsd44::{AF_INET, AF_INET6};
+use libc::consts::os::posix88::{EINVAL, ESRCH};
+use libc::types::common::c95::c_void;
+use libc::types::os::arch::c95::{c_int, c_ulong};
+use net::CidrError;
+use chrono::{TimeZone, UTC};
+use super::{Addr, AStats, Command, Device, StateKill, Table, ioctl_error};
+use super::{PFR_TFLAG_PERSIST, PFR_TFLAG_COUNTERS};
+
+#[test]
+fn addr_ipv4_layout() {
+    let addr: Addr = "203.0.113.0/24".parse().unwrap();
+    assert_eq!([203, 0, 113, 0], &addr.addr[..4]);
+    assert_eq!([0; 12],          &addr.addr[4..]);
+    assert_eq!(AF_INET as u8, addr.af);
+    assert_eq!(24, addr.net);
+    assert_eq!(0,  addr.not);
+}
+
+#[test]
+fn addr_ipv6_layout() {
+    let addr: Addr = "!2001:db8::/48".parse().unwrap();
+    let bytes = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    assert_eq!(bytes, addr.addr);
+    assert_eq!(AF_INET6 as u8, addr.af);
+    assert_eq!(48, addr.net);
+    assert_eq!(1,  addr.not);
+}
+
+#[test]
+fn addr_host() {
+    let addr: Addr = "2404:6800:4004:814::200e".parse().unwrap();
+    assert_eq!(128, addr.net);
+    assert_eq!(0x0e, addr.addr[15]);
+    assert_eq!(32, "1.2.3.4".parse::<Addr>().unwrap().net);
+}
+
+#[test]
+fn addr_round_trip() {
+    let entries = [
+        "203.0.113.0/24",
+        "!2001:db8::/48",
+        "!192.0.2.1",
+        "2404:6800:4004:814::200e",
+    ];
+
+    for entry in &entries {
+        let addr: Addr = entry.parse().unwrap();
+        assert_eq!(*entry, addr.to_string());
+        assert_eq!(entry.starts_with("!"), addr.is_negated());
+    }
+}
+
+#[test]
+fn addr_invalid() {
+    assert_eq!(CidrError::HostBits,      "203.0.113.1/24".parse::<Addr>().unwrap_err());
+    assert_eq!(CidrError::InvalidPrefix, "!2001:db8::/129".parse::<Addr>().unwrap_err());
+    assert_eq!(CidrError::InvalidAddr,   "!!192.0.2.1".parse::<Addr>().unwrap_err());
+}
+
+#[test]
+fn state_kill_src() {
+    let kill = StateKill::new("203.0.113.0/24".parse().unwrap());
+    assert_eq!(AF_INET as u8, kill.af);
+    assert_eq!([203, 0, 113, 0],   &kill.src.addr.addr[..4]);
+    assert_eq!([255, 255, 255, 0], &kill.src.addr.mask[..4]);
+    assert_eq!([0; 12],            &kill.src.addr.mask[4..]);
+}
+
+#[test]
+fn table_anchor() {
+    let table = Table::new("bruteforce", Some("irongate/ssh")).unwrap();
+    assert_eq!("bruteforce",   table.name());
+    assert_eq!("irongate/ssh", table.anchor());
+    assert_eq!("",             Table::new("bruteforce", None).unwrap().anchor());
+}
+
+#[test]
+fn table_flags() {
+    let table = Table::new("bruteforce", None).unwrap().with_flags(PFR_TFLAG_PERSIST | PFR_TFLAG_COUNTERS);
+    assert_eq!(0x41, table.flags);
+    assert_eq!(PFR_TFLAG_PERSIST | PFR_TFLAG_COUNTERS, table.flags());
+}
+
+#[test]
+fn command_with_table() {
+    let cmd = Command::with_table("bruteforce", Some("irongate")).unwrap();
+    assert_eq!("bruteforce", cmd.table.name());
+    assert_eq!("irongate",   cmd.table.anchor());
+    assert_eq!("irongate",   Command::with_anchor(Some("irongate")).unwrap().table.anchor());
+}
+
+#[test]
+fn table_too_long() {
+    let name: String = (0..31).map(|_| 'x').collect();
+    assert_eq!(31, Table::new(&name, None).unwrap().name().len());
+
+    let name: String = (0..32).map(|_| 'x').collect();
+    assert!(Table::new(&name, None).is_err());
+
+    let anchor: String = (0..1024).map(|_| 'x').collect();
+    assert!(Table::new("bruteforce", Some(&anchor)).is_err());
+    assert!(Command::with_anchor(Some(&anchor)).is_err());
+}
+
+#[test]
+fn missing_table_error() {
+    assert_eq!(ErrorKind::NotFound, ioctl_error(Error::from_raw_os_error(ESRCH)).kind());
+    assert_eq!(Some(EINVAL), ioctl_error(Error::from_raw_os_error(EINVAL)).raw_os_error());
+}
+
+#[test]
+fn astats_counters() {
+    let mut stats: AStats = unsafe { mem::zeroed() };
+    stats.addr  = "192.0.2.1".parse().unwrap();
+    stats.tzero = 1430000000;
+    stats.packets[0][0] = 3;
+    stats.packets[0][1] = 100;
+    stats.packets[1][0] = 2;
+    stats.packets[1][1] = 50;
+    stats.bytes[0][0]   = 300;
+    stats.bytes[0][1]   = 10000;
+    stats.bytes[1][0]   = 200;
+    stats.bytes[1][1]   = 5000;
+
+    assert_eq!(5,   stats.blocked_packets());
+    assert_eq!(500, stats.blocked_bytes());
+    assert_eq!(UTC.timestamp(1430000000, 0), stats.added());
+}
+
+// Simulates a table whose size changes between ioctl calls, taking the
+// number of entries present at each call from `sizes`.
+struct Changing {
+    sizes: RefCell<Vec<usize>>,
+}
+
+impl Device for Changing {
+    unsafe fn ioctl(&self, _: c_ulong, arg: *mut c_void) -> Result<(), Error> {
+        let cmd = &mut *(arg as *mut Command);
+        let n = match self.sizes.borrow_mut().pop() {
+            Some(n) => n,
+            None    => return Err(Error::new(ErrorKind::Other, "unexpected ioctl")),
+        };
+
+        if n <= cmd.size as usize {
+            let buffer = cmd.buffer as *mut Addr;
+            for i in 0..n {
+                *buffer.offset(i as isize) = Addr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, i as u8)));
+            }
+        }
+        cmd.size = n as c_int;
+        Ok(())
+    }
+}
+
+fn get(sizes: &[usize]) -> Result<Vec<Addr>, Error> {
+    let dev = Changing { sizes: RefCell::new(sizes.iter().rev().cloned().collect()) };
+    super::get(&dev, 0, &mut Command::new())
+}
+
+#[test]
+fn get_stable() {
+    let addrs = get(&[2, 2]).unwrap();
+    assert_eq!(2, addrs.len());
+    assert_eq!("192.0.2.1", addrs[1].to_string());
+}
+
+#[test]
+fn get_grows() {
+    let addrs = get(&[2, 3, 3]).unwrap();
+    assert_eq!(3, addrs.len());
+    assert_eq!("192.0.2.2", addrs[2].to_string());
+}
+
+#[test]
+fn get_shrinks() {
+    assert_eq!(1, get(&[3, 1]).unwrap().len());
+    assert_eq!(0, get(&[0, 0]).unwrap().len());
+}
+
+#[test]
+fn get_unsettled() {
+    let sizes: Vec<usize> = (1..20).collect();
+    assert_eq!(ErrorKind::Interrupted, get(&sizes).unwrap_err().kind());
+}
@@ -2,11 +2,13 @@
 
 use std::ffi::OsStr;
 use std::io::Error;
+use std::mem;
 
 use libc::consts::os::c95::SEEK_CUR;
 use libc::consts::os::posix88::O_RDONLY;
-use libc::funcs::posix88::{fcntl, unistd};
+use libc::funcs::posix88::{fcntl, stat_, unistd};
 use libc::types::os::arch::c95::c_int;
+use libc::types::os::arch::posix01::stat;
 use libc::types::os::arch::posix88::off_t;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -40,4 +42,23 @@ impl Fd {
             }
         }
     }
+
+    pub fn position(self) -> Result<off_t, Error> {
+        unsafe {
+            match unistd::lseek(self.0 as c_int, 0, SEEK_CUR) {
+                -1  => Err(Error::last_os_error()),
+                pos => Ok(pos)
+            }
+        }
+    }
+
+    pub fn size(self) -> Result<off_t, Error> {
+        unsafe {
+            let mut stat: stat = mem::zeroed();
+            match stat_::fstat(self.0 as c_int, &mut stat) {
+                -1 => Err(Error::last_os_error()),
+                 _ => Ok(stat.st_size)
+            }
+        }
+    }
 }
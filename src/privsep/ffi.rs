@@ -0,0 +1,112 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::os::unix::io::FromRawFd;
+use std::path::Path;
+use std::ptr;
+
+use libc::consts::os::bsd44::{AF_UNIX, SOCK_STREAM};
+use libc::types::os::arch::c95::{c_char, c_int};
+use libc::types::os::arch::posix88::{gid_t, pid_t, uid_t};
+
+// Only the leading fields of struct passwd, which have the same layout
+// on every supported OS. The BSDs append more fields than Linux.
+#[repr(C)]
+struct passwd {
+    pw_name:   *mut c_char,
+    pw_passwd: *mut c_char,
+    pw_uid:    uid_t,
+    pw_gid:    gid_t,
+}
+
+#[cfg(target_os = "linux")]
+type ngroups_t = ::libc::types::os::arch::c95::size_t;
+#[cfg(not(target_os = "linux"))]
+type ngroups_t = c_int;
+
+const LOG_PID:    c_int = 0x01;
+const LOG_NDELAY: c_int = 0x08;
+const LOG_DAEMON: c_int = 3 << 3;
+
+extern {
+    #[link_name = "socketpair"]
+    fn c_socketpair(domain: c_int, kind: c_int, protocol: c_int, fds: *mut c_int) -> c_int;
+    #[link_name = "fork"]
+    fn c_fork() -> pid_t;
+    fn waitpid(pid: pid_t, status: *mut c_int, options: c_int) -> pid_t;
+    fn getpwnam(name: *const c_char) -> *mut passwd;
+    fn chroot(path: *const c_char) -> c_int;
+    fn chdir(path: *const c_char) -> c_int;
+    fn setgroups(n: ngroups_t, groups: *const gid_t) -> c_int;
+    fn setgid(gid: gid_t) -> c_int;
+    fn setuid(uid: uid_t) -> c_int;
+    fn openlog(ident: *const c_char, option: c_int, facility: c_int);
+}
+
+pub fn socketpair() -> Result<(File, File), Error> {
+    let mut fds = [-1 as c_int; 2];
+    unsafe {
+        try!(check(c_socketpair(AF_UNIX, SOCK_STREAM, 0, fds.as_mut_ptr())));
+        Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])))
+    }
+}
+
+// Returns the child's pid in the parent and 0 in the child.
+pub fn fork() -> Result<pid_t, Error> {
+    match unsafe { c_fork() } {
+        -1  => Err(Error::last_os_error()),
+        pid => Ok(pid),
+    }
+}
+
+pub fn wait(pid: pid_t) -> Result<c_int, Error> {
+    let mut status = 0;
+    loop {
+        match unsafe { waitpid(pid, &mut status, 0) } {
+            -1 => match Error::last_os_error() {
+                ref e if e.kind() == ErrorKind::Interrupted => continue,
+                e                                           => return Err(e),
+            },
+            _  => return Ok(status),
+        }
+    }
+}
+
+// Chroot to `root` and switch to `user`, opening the syslog connection
+// first since /dev/log is out of reach afterwards.
+pub fn confine(user: &str, root: &Path) -> Result<(), Error> {
+    let name = match CString::new(user) {
+        Ok(name) => name,
+        Err(..)  => return Err(Error::new(ErrorKind::InvalidInput, "invalid user name")),
+    };
+    let root = root.as_os_str().to_cstring().unwrap();
+
+    unsafe {
+        let pw = getpwnam(name.as_ptr());
+        if pw.is_null() {
+            return Err(Error::new(ErrorKind::NotFound, format!("unknown user: {}", user)));
+        }
+        let (uid, gid) = ((*pw).pw_uid, (*pw).pw_gid);
+
+        openlog(ptr::null(), LOG_PID | LOG_NDELAY, LOG_DAEMON);
+        try!(check(chroot(root.as_ptr())));
+        try!(check(chdir(b"/\0".as_ptr() as *const c_char)));
+        try!(check(setgroups(1, &gid)));
+        try!(check(setgid(gid)));
+        try!(check(setuid(uid)));
+
+        if uid != 0 && setuid(0) != -1 {
+            return Err(Error::new(ErrorKind::Other, "failed to drop privileges"));
+        }
+    }
+    Ok(())
+}
+
+fn check(rc: c_int) -> Result<(), Error> {
+    match rc {
+        -1 => Err(Error::last_os_error()),
+        _  => Ok(()),
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+mod ffi;
+
+use std::fmt::{self, Display, Formatter};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use chrono::Duration;
+
+use block::Blocker;
+use net::Cidr;
+
+pub use self::ffi::{confine, fork, socketpair, wait};
+
+// Requests are fixed 24-byte frames:
+//
+//   0  version
+//   1  op, 1 to ban and 2 to unban
+//   2  address family, 4 or 6
+//   3  prefix length
+//   4  ban duration in seconds, big-endian, 0 when permanent or unbanning
+//   8  address, IPv4 in the first 4 bytes and the rest zero
+//
+// Replies are a version byte followed by a status byte.
+pub const VERSION:     u8    = 1;
+pub const REQUEST_LEN: usize = 24;
+pub const REPLY_LEN:   usize = 2;
+
+const OP_BAN:   u8 = 1;
+const OP_UNBAN: u8 = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Request {
+    Ban(Cidr, Option<Duration>),
+    Unban(Cidr),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reply {
+    Unchanged,
+    Changed,
+    Missing,
+    Failed,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WireError {
+    Version(u8),
+    Op(u8),
+    Family(u8),
+    PrefixLen(u8),
+    HostBits,
+    Padding,
+    Duration,
+    Status(u8),
+}
+
+impl Request {
+    pub fn encode(&self) -> [u8; REQUEST_LEN] {
+        let mut buf = [0u8; REQUEST_LEN];
+        let (op, cidr, secs) = match *self {
+            Request::Ban(cidr, ban) => (OP_BAN, cidr, ban.map_or(0, seconds)),
+            Request::Unban(cidr)    => (OP_UNBAN, cidr, 0),
+        };
+
+        buf[0] = VERSION;
+        buf[1] = op;
+        buf[3] = cidr.len();
+        for n in 0..4 {
+            buf[4 + n] = (secs >> (24 - 8 * n)) as u8;
+        }
+        match cidr.addr() {
+            IpAddr::V4(addr) => {
+                buf[2] = 4;
+                for (n, octet) in addr.octets().iter().enumerate() {
+                    buf[8 + n] = *octet;
+                }
+            },
+            IpAddr::V6(addr) => {
+                buf[2] = 6;
+                for (n, segment) in addr.segments().iter().enumerate() {
+                    buf[8 + n * 2]     = (segment >> 8) as u8;
+                    buf[8 + n * 2 + 1] = *segment as u8;
+                }
+            },
+        }
+        buf
+    }
+
+    pub fn decode(buf: &[u8; REQUEST_LEN]) -> Result<Request, WireError> {
+        if buf[0] != VERSION {
+            return Err(WireError::Version(buf[0]));
+        }
+
+        let b = &buf[8..];
+        let (addr, max) = match buf[2] {
+            4 => {
+                if b[4..].iter().any(|b| *b != 0) {
+                    return Err(WireError::Padding);
+                }
+                (IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3])), 32)
+            },
+            6 => {
+                let s = |n: usize| (b[n * 2] as u16) << 8 | b[n * 2 + 1] as u16;
+                (IpAddr::V6(Ipv6Addr::new(s(0), s(1), s(2), s(3), s(4), s(5), s(6), s(7))), 128)
+            },
+            family => return Err(WireError::Family(family)),
+        };
+
+        if buf[3] > max {
+            return Err(WireError::PrefixLen(buf[3]));
+        }
+        let cidr = Cidr::new(addr, buf[3]);
+        if cidr.addr() != addr {
+            return Err(WireError::HostBits);
+        }
+
+        let secs = buf[4..8].iter().fold(0u32, |secs, b| secs << 8 | *b as u32);
+        match buf[1] {
+            OP_BAN if secs == 0   => Ok(Request::Ban(cidr, None)),
+            OP_BAN                => Ok(Request::Ban(cidr, Some(Duration::seconds(secs as i64)))),
+            OP_UNBAN if secs == 0 => Ok(Request::Unban(cidr)),
+            OP_UNBAN              => Err(WireError::Duration),
+            op                    => Err(WireError::Op(op)),
+        }
+    }
+}
+
+impl Reply {
+    pub fn encode(&self) -> [u8; REPLY_LEN] {
+        let status = match *self {
+            Reply::Unchanged => 0,
+            Reply::Changed   => 1,
+            Reply::Missing   => 2,
+            Reply::Failed    => 3,
+        };
+        [VERSION, status]
+    }
+
+    pub fn decode(buf: &[u8; REPLY_LEN]) -> Result<Reply, WireError> {
+        match (buf[0], buf[1]) {
+            (VERSION, 0) => Ok(Reply::Unchanged),
+            (VERSION, 1) => Ok(Reply::Changed),
+            (VERSION, 2) => Ok(Reply::Missing),
+            (VERSION, 3) => Ok(Reply::Failed),
+            (VERSION, n) => Err(WireError::Status(n)),
+            (version, _) => Err(WireError::Version(version)),
+        }
+    }
+}
+
+// Firewall access for the unprivileged process. Bans and unbans go to the
+// privileged process, which owns the table, so listing it isn't possible.
+pub struct Client<S: Read + Write> {
+    stream: S,
+}
+
+impl<S: Read + Write> Client<S> {
+    pub fn new(stream: S) -> Client<S> {
+        Client {
+            stream: stream,
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Result<bool, Error> {
+        try!(self.stream.write_all(&request.encode()));
+
+        let mut buf = [0u8; REPLY_LEN];
+        if !try!(read_frame(&mut self.stream, &mut buf)) {
+            return Err(Error::new(ErrorKind::BrokenPipe, "privileged process exited"));
+        }
+
+        match Reply::decode(&buf) {
+            Ok(Reply::Unchanged) => Ok(false),
+            Ok(Reply::Changed)   => Ok(true),
+            Ok(Reply::Missing)   => Err(Error::new(ErrorKind::NotFound, "table does not exist")),
+            Ok(Reply::Failed)    => Err(Error::new(ErrorKind::Other, "firewall update failed")),
+            Err(e)               => Err(Error::new(ErrorKind::InvalidData, format!("invalid reply: {}", e))),
+        }
+    }
+}
+
+impl<S: Read + Write> Blocker for Client<S> {
+    fn ensure_table(&mut self, _table: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn add(&mut self, _table: &str, addrs: &[Cidr], timeout: Option<Duration>) -> Result<usize, Error> {
+        let mut n = 0;
+        for cidr in addrs {
+            if try!(self.call(Request::Ban(*cidr, timeout))) {
+                n += 1;
+            }
+        }
+        Ok(n)
+    }
+
+    fn remove(&mut self, _table: &str, addrs: &[Cidr]) -> Result<usize, Error> {
+        let mut n = 0;
+        for cidr in addrs {
+            if try!(self.call(Request::Unban(*cidr))) {
+                n += 1;
+            }
+        }
+        Ok(n)
+    }
+
+    fn list(&mut self, _table: &str) -> Result<Vec<Cidr>, Error> {
+        Ok(Vec::new())
+    }
+}
+
+// The privileged end, reading requests and writing replies. Any malformed
+// request ends the session.
+pub struct Server<S: Read + Write> {
+    stream: S,
+}
+
+impl<S: Read + Write> Server<S> {
+    pub fn new(stream: S) -> Server<S> {
+        Server {
+            stream: stream,
+        }
+    }
+
+    pub fn next(&mut self) -> Result<Option<Request>, Error> {
+        let mut buf = [0u8; REQUEST_LEN];
+        if !try!(read_frame(&mut self.stream, &mut buf)) {
+            return Ok(None);
+        }
+
+        match Request::decode(&buf) {
+            Ok(request) => Ok(Some(request)),
+            Err(e)      => Err(Error::new(ErrorKind::InvalidData, format!("invalid request: {}", e))),
+        }
+    }
+
+    pub fn reply(&mut self, reply: Reply) -> Result<(), Error> {
+        self.stream.write_all(&reply.encode())
+    }
+}
+
+// The path of `path` once the process is chrooted to `root`.
+pub fn confined(path: &Path, root: &Path) -> Option<PathBuf> {
+    let (path, root) = match (path.to_str(), root.to_str()) {
+        (Some(path), Some(root)) => (path, root.trim_right_matches('/')),
+        _                        => return None,
+    };
+
+    match path.starts_with(root) && path[root.len()..].starts_with('/') {
+        true  => Some(PathBuf::from(&path[root.len()..])),
+        false => None,
+    }
+}
+
+// Fill `buf` completely, returning false on a clean end of stream.
+fn read_frame<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<bool, Error> {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(&mut buf[n..]) {
+            Ok(0) if n == 0                                  => return Ok(false),
+            Ok(0)                                            => return Err(Error::new(ErrorKind::InvalidData, "truncated frame")),
+            Ok(len)                                          => n += len,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e)                                           => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+fn seconds(d: Duration) -> u32 {
+    match d.num_seconds() {
+        secs if secs < 1                       => 1,
+        secs if secs > u32::max_value() as i64 => u32::max_value(),
+        secs                                   => secs as u32,
+    }
+}
+
+impl Display for WireError {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            WireError::Version(v)   => write!(fmt, "unsupported version {}", v),
+            WireError::Op(op)       => write!(fmt, "unknown op {}", op),
+            WireError::Family(f)    => write!(fmt, "unknown address family {}", f),
+            WireError::PrefixLen(n) => write!(fmt, "invalid prefix length {}", n),
+            WireError::HostBits     => write!(fmt, "host bits set"),
+            WireError::Padding      => write!(fmt, "non-zero padding"),
+            WireError::Duration     => write!(fmt, "unban with a duration"),
+            WireError::Status(n)    => write!(fmt, "unknown status {}", n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
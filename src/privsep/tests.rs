@@ -0,0 +1,119 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use chrono::Duration;
+use block::Blocker;
+use net::Cidr;
+use super::*;
+
+#[test]
+fn encode_ban_v4() {
+    let request = Request::Ban(cidr("193.107.17.0/24"), Some(Duration::minutes(10)));
+    assert_eq!([1, 1, 4, 24, 0, 0, 2, 88, 193, 107, 17, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], request.encode());
+}
+
+#[test]
+fn encode_unban_v6() {
+    let request = Request::Unban(cidr("2404:6800:4004:814::1"));
+    assert_eq!([1, 2, 6, 128, 0, 0, 0, 0, 0x24, 0x04, 0x68, 0x00,
+                0x40, 0x04, 0x08, 0x14, 0, 0, 0, 0, 0, 0, 0, 1], request.encode());
+}
+
+#[test]
+fn round_trip() {
+    let requests = [
+        Request::Ban(cidr("193.107.17.72"), None),
+        Request::Ban(cidr("193.107.17.0/24"), Some(Duration::hours(1))),
+        Request::Ban(cidr("2404:6800:4004:814::/64"), Some(Duration::days(1))),
+        Request::Unban(cidr("8.254.73.28")),
+        Request::Unban(cidr("::/0")),
+    ];
+    for request in &requests {
+        assert_eq!(Ok(*request), Request::decode(&request.encode()));
+    }
+
+    for reply in &[Reply::Unchanged, Reply::Changed, Reply::Missing, Reply::Failed] {
+        assert_eq!(Ok(*reply), Reply::decode(&reply.encode()));
+    }
+}
+
+#[test]
+fn ban_duration_clamped() {
+    let encode = |d| Request::Ban(cidr("8.254.73.28"), Some(d)).encode();
+    assert_eq!([0, 0, 0, 1], &encode(Duration::zero())[4..8]);
+    assert_eq!([0xff, 0xff, 0xff, 0xff], &encode(Duration::days(100000))[4..8]);
+}
+
+#[test]
+fn decode_invalid() {
+    let valid = Request::Ban(cidr("193.107.17.0/24"), None).encode();
+    let check = |n: usize, b: u8, err: WireError| {
+        let mut buf = valid;
+        buf[n] = b;
+        assert_eq!(Err(err), Request::decode(&buf));
+    };
+
+    check(0, 2, WireError::Version(2));
+    check(1, 3, WireError::Op(3));
+    check(2, 5, WireError::Family(5));
+    check(3, 33, WireError::PrefixLen(33));
+    check(11, 1, WireError::HostBits);
+    check(12, 1, WireError::Padding);
+    check(23, 1, WireError::Padding);
+
+    let mut buf = Request::Unban(cidr("8.254.73.28")).encode();
+    buf[7] = 1;
+    assert_eq!(Err(WireError::Duration), Request::decode(&buf));
+
+    let mut buf = Request::Unban(cidr("2404:6800:4004:814::/64")).encode();
+    buf[3] = 129;
+    assert_eq!(Err(WireError::PrefixLen(129)), Request::decode(&buf));
+    buf[3] = 64;
+    buf[23] = 1;
+    assert_eq!(Err(WireError::HostBits), Request::decode(&buf));
+
+    assert_eq!(Err(WireError::Version(0)), Reply::decode(&[0, 1]));
+    assert_eq!(Err(WireError::Status(4)), Reply::decode(&[VERSION, 4]));
+}
+
+#[test]
+fn server_requests() {
+    let mut input = Vec::new();
+    input.extend(Request::Ban(cidr("8.254.73.28"), None).encode().iter().cloned());
+    input.extend(Request::Unban(cidr("8.254.73.28")).encode().iter().cloned());
+
+    let mut server = Server::new(Cursor::new(input));
+    assert_eq!(Some(Request::Ban(cidr("8.254.73.28"), None)), server.next().unwrap());
+    assert_eq!(Some(Request::Unban(cidr("8.254.73.28"))), server.next().unwrap());
+    assert_eq!(None, server.next().unwrap());
+}
+
+#[test]
+fn server_truncated() {
+    let request = Request::Ban(cidr("8.254.73.28"), None).encode();
+    let mut server = Server::new(Cursor::new(request[..10].to_vec()));
+    assert!(server.next().is_err());
+}
+
+#[test]
+fn client_exited() {
+    let mut client = Client::new(Cursor::new(Vec::new()));
+    assert!(client.add("irongate", &[cidr("8.254.73.28")], None).is_err());
+}
+
+#[test]
+fn confined_path() {
+    let confined = |path, root| super::confined(Path::new(path), Path::new(root));
+    assert_eq!(Some(PathBuf::from("/auth.log")), confined("/var/log/auth.log", "/var/log"));
+    assert_eq!(Some(PathBuf::from("/auth.log")), confined("/var/log/auth.log", "/var/log/"));
+    assert_eq!(Some(PathBuf::from("/log/auth.log")), confined("/var/log/auth.log", "/var"));
+    assert_eq!(Some(PathBuf::from("/var/log/auth.log")), confined("/var/log/auth.log", "/"));
+    assert_eq!(None, confined("/var/logs/auth.log", "/var/log"));
+    assert_eq!(None, confined("/var/log", "/var/log"));
+}
+
+fn cidr(s: &str) -> Cidr {
+    s.parse().unwrap()
+}
@@ -0,0 +1,103 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fmt::{self, Display, Formatter};
+use std::net::IpAddr;
+use chrono::{DateTime, Duration, TimeZone};
+
+use cms::TimeWindowCMS;
+use net::Cidr;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rules {
+    pub limit:        u64,
+    pub prefix_limit: u64,
+    pub prefix4:      u8,
+    pub prefix6:      u8,
+    pub period:       Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rule {
+    Host(u64),
+    Prefix(u8, u64),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Decision {
+    pub cidr:  Cidr,
+    pub count: u64,
+    pub rule:  Rule,
+}
+
+pub struct Counter {
+    rules: Rules,
+    hosts: TimeWindowCMS<'static, IpAddr>,
+    nets:  TimeWindowCMS<'static, Cidr>,
+}
+
+impl Rules {
+    pub fn prefix(&self, addr: IpAddr) -> Option<Cidr> {
+        let len = match addr {
+            IpAddr::V4(..) => self.prefix4,
+            IpAddr::V6(..) => self.prefix6,
+        };
+        match self.prefix_limit {
+            0 => None,
+            _ => Some(Cidr::new(addr, len)),
+        }
+    }
+}
+
+impl Counter {
+    pub fn new(rules: Rules) -> Counter {
+        Counter {
+            rules: rules,
+            hosts: TimeWindowCMS::new(rules.period, &RESOLUTION),
+            nets:  TimeWindowCMS::new(rules.period, &RESOLUTION),
+        }
+    }
+
+    pub fn count<Z: TimeZone>(&mut self, when: DateTime<Z>, addr: IpAddr) -> Vec<Decision> {
+        let mut decisions = Vec::new();
+
+        let count = self.hosts.add(when.clone(), addr);
+        if count > self.rules.limit {
+            decisions.push(Decision {
+                cidr:  Cidr::host(addr),
+                count: count,
+                rule:  Rule::Host(self.rules.limit),
+            });
+        }
+
+        if let Some(net) = self.rules.prefix(addr) {
+            let count = self.nets.add(when, net);
+            if count > self.rules.prefix_limit {
+                decisions.push(Decision {
+                    cidr:  net,
+                    count: count,
+                    rule:  Rule::Prefix(net.len(), self.rules.prefix_limit),
+                });
+            }
+        }
+
+        decisions
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Rule::Host(limit)        => write!(fmt, "host limit {}", limit),
+            Rule::Prefix(len, limit) => write!(fmt, "/{} limit {}", len, limit),
+        }
+    }
+}
+
+static RESOLUTION: fn(&Duration) -> i64 = seconds;
+
+fn seconds(d: &Duration) -> i64 {
+    d.num_seconds()
+}
+
+#[cfg(test)]
+mod tests;
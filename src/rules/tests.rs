@@ -0,0 +1,49 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::net::IpAddr;
+use chrono::{Duration, UTC};
+use net::Cidr;
+use super::*;
+
+#[test]
+fn host_limit() {
+    let now = UTC::now();
+    let mut counter = Counter::new(rules(2, 0));
+    let addr = addr("193.107.17.72");
+
+    assert!(counter.count(now, addr).is_empty());
+    assert!(counter.count(now, addr).is_empty());
+    assert_eq!(vec![Decision {
+        cidr:  Cidr::host(addr),
+        count: 3,
+        rule:  Rule::Host(2),
+    }], counter.count(now, addr));
+}
+
+#[test]
+fn prefix_limit() {
+    let now = UTC::now();
+    let mut counter = Counter::new(rules(5, 2));
+
+    assert!(counter.count(now, addr("2404:6800:4004:814::1")).is_empty());
+    assert!(counter.count(now, addr("2404:6800:4004:814::2")).is_empty());
+
+    let decisions = counter.count(now, addr("2404:6800:4004:814::3"));
+    assert_eq!(1, decisions.len());
+    assert_eq!("2404:6800:4004:814::/64", decisions[0].cidr.to_string());
+    assert_eq!("/64 limit 2", decisions[0].rule.to_string());
+}
+
+fn rules(limit: u64, prefix_limit: u64) -> Rules {
+    Rules {
+        limit:        limit,
+        prefix_limit: prefix_limit,
+        prefix4:      24,
+        prefix6:      64,
+        period:       Duration::minutes(1),
+    }
+}
+
+fn addr(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
@@ -0,0 +1,143 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::io::Error;
+use std::mem;
+use std::path::PathBuf;
+
+use libc::consts::os::bsd44::{AF_INET, SOCK_DGRAM};
+use libc::types::os::arch::c95::{c_char, c_int, c_long, c_ulong};
+use super::Insn;
+
+const PR_SET_SECCOMP:      c_int   = 22;
+const PR_SET_NO_NEW_PRIVS: c_int   = 38;
+const SECCOMP_MODE_FILTER: c_ulong = 2;
+
+const SYS_LANDLOCK_CREATE_RULESET: c_long = 444;
+const SYS_LANDLOCK_ADD_RULE:       c_long = 445;
+const SYS_LANDLOCK_RESTRICT_SELF:  c_long = 446;
+const LANDLOCK_RULE_PATH_BENEATH:  c_int  = 1;
+
+const O_PATH:     c_int = 0o10000000;
+const O_CLOEXEC:  c_int = 0o2000000;
+const EPERM:      i32   = 1;
+const ENOSYS:     i32   = 38;
+const EOPNOTSUPP: i32   = 95;
+
+// Filesystem rights from landlock ABI v1, all of which are handled so
+// anything not granted below is denied.
+const ACCESS_FS_WRITE_FILE:  u64 = 1 << 1;
+const ACCESS_FS_READ_FILE:   u64 = 1 << 2;
+const ACCESS_FS_READ_DIR:    u64 = 1 << 3;
+const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+const ACCESS_FS_MAKE_REG:    u64 = 1 << 8;
+const ACCESS_FS_ALL:         u64 = (1 << 13) - 1;
+
+const ACCESS_READ:  u64 = ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+const ACCESS_WRITE: u64 = ACCESS_READ | ACCESS_FS_WRITE_FILE | ACCESS_FS_REMOVE_FILE | ACCESS_FS_MAKE_REG;
+
+#[repr(C)]
+struct landlock_ruleset_attr {
+    handled_access_fs: u64,
+}
+
+#[repr(C, packed)]
+struct landlock_path_beneath_attr {
+    allowed_access: u64,
+    parent_fd:      c_int,
+}
+
+#[repr(C)]
+struct sock_fprog {
+    len:    u16,
+    filter: *const Insn,
+}
+
+extern {
+    fn prctl(option: c_int, ...) -> c_int;
+    fn syscall(num: c_long, ...) -> c_long;
+    fn open(path: *const c_char, flags: c_int, ...) -> c_int;
+    fn close(fd: c_int) -> c_int;
+    fn socket(domain: c_int, kind: c_int, protocol: c_int) -> c_int;
+    #[link_name = "_exit"]
+    pub fn exit(status: c_int) -> !;
+}
+
+pub fn no_new_privs() -> Result<(), Error> {
+    check(unsafe { prctl(PR_SET_NO_NEW_PRIVS, 1 as c_ulong, 0 as c_ulong, 0 as c_ulong, 0 as c_ulong) })
+}
+
+pub fn seccomp(filter: &[Insn]) -> Result<(), Error> {
+    let prog = sock_fprog {
+        len:    filter.len() as u16,
+        filter: filter.as_ptr(),
+    };
+    check(unsafe { prctl(PR_SET_SECCOMP, SECCOMP_MODE_FILTER, &prog as *const sock_fprog) })
+}
+
+// Returns false when the kernel doesn't support landlock.
+pub fn landlock(read: &[PathBuf], write: &[PathBuf]) -> Result<bool, Error> {
+    let attr = landlock_ruleset_attr {
+        handled_access_fs: ACCESS_FS_ALL,
+    };
+
+    unsafe {
+        let size    = mem::size_of::<landlock_ruleset_attr>();
+        let ruleset = syscall(SYS_LANDLOCK_CREATE_RULESET, &attr as *const landlock_ruleset_attr, size, 0u32) as c_int;
+        if ruleset == -1 {
+            let e = Error::last_os_error();
+            return match e.raw_os_error() {
+                Some(ENOSYS) | Some(EOPNOTSUPP) => Ok(false),
+                _                               => Err(e),
+            };
+        }
+
+        let rules = read.iter().map(|dir| (dir, ACCESS_READ)).chain(write.iter().map(|dir| (dir, ACCESS_WRITE)));
+        for (dir, access) in rules {
+            if let Err(e) = add_rule(ruleset, dir, access) {
+                close(ruleset);
+                return Err(e);
+            }
+        }
+
+        let rc = syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset, 0u32) as c_int;
+        close(ruleset);
+        try!(check(rc));
+    }
+    Ok(true)
+}
+
+// Install the filter and try a forbidden call, for the check run in a
+// forked child: 0 when refused with EPERM, 1 when allowed, 2 when the
+// filter couldn't be installed.
+pub unsafe fn refused(filter: &[Insn]) -> c_int {
+    if no_new_privs().is_err() || seccomp(filter).is_err() {
+        return 2;
+    }
+
+    match socket(AF_INET, SOCK_DGRAM, 0) {
+        -1 if Error::last_os_error().raw_os_error() == Some(EPERM) => 0,
+        _                                                         => 1,
+    }
+}
+
+unsafe fn add_rule(ruleset: c_int, dir: &PathBuf, access: u64) -> Result<(), Error> {
+    let path = dir.as_os_str().to_cstring().unwrap();
+    let fd   = open(path.as_ptr(), O_PATH | O_CLOEXEC);
+    try!(check(fd));
+
+    let beneath = landlock_path_beneath_attr {
+        allowed_access: access,
+        parent_fd:      fd,
+    };
+    let rc = syscall(SYS_LANDLOCK_ADD_RULE, ruleset, LANDLOCK_RULE_PATH_BENEATH,
+                     &beneath as *const landlock_path_beneath_attr, 0u32) as c_int;
+    close(fd);
+    check(rc)
+}
+
+fn check(rc: c_int) -> Result<(), Error> {
+    match rc {
+        -1 => Err(Error::last_os_error()),
+        _  => Ok(()),
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+pub mod syscalls;
+#[cfg(target_os = "linux")]
+mod ffi;
+
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "linux")]
+use privsep::{fork, wait};
+
+// struct sock_filter, one BPF instruction.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Insn {
+    pub code: u16,
+    pub jt:   u8,
+    pub jf:   u8,
+    pub k:    u32,
+}
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JEQ_K:    u16 = 0x15;
+const BPF_RET_K:    u16 = 0x06;
+
+// Offsets of the syscall number, audit arch and the low half of the
+// first argument in struct seccomp_data.
+const NR_OFFSET:   u32 = 0;
+const ARCH_OFFSET: u32 = 4;
+const ARG0_OFFSET: u32 = 16;
+
+pub const SECCOMP_RET_KILL_PROCESS: u32 = 0x80000000;
+pub const SECCOMP_RET_ERRNO:        u32 = 0x00050000;
+pub const SECCOMP_RET_ALLOW:        u32 = 0x7fff0000;
+
+const EPERM:   u32 = 1;
+const AF_UNIX: u32 = 1;
+
+pub struct Sandbox {
+    read:  Vec<PathBuf>,
+    write: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn new() -> Sandbox {
+        Sandbox {
+            read:  Vec::new(),
+            write: Vec::new(),
+        }
+    }
+
+    // Allow reading files in the directory holding `file`.
+    pub fn read(&mut self, file: &Path) {
+        self.read.push(dir(file));
+    }
+
+    // Allow creating, replacing and writing files in the directory
+    // holding `file`.
+    pub fn write(&mut self, file: &Path) {
+        self.write.push(dir(file));
+    }
+
+    pub fn dirs(&self) -> (&[PathBuf], &[PathBuf]) {
+        (&self.read, &self.write)
+    }
+
+    // Restrict file access with landlock and system calls with seccomp,
+    // returning false if the kernel lacks landlock and only system calls
+    // are restricted.
+    #[cfg(target_os = "linux")]
+    pub fn enable(&self) -> Result<bool, Error> {
+        let (arch, socket, allowed) = try!(arch());
+        try!(ffi::no_new_privs());
+        let landlock = try!(ffi::landlock(&self.read, &self.write));
+        try!(ffi::seccomp(&filter(arch, socket, allowed)));
+        Ok(landlock)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn enable(&self) -> Result<bool, Error> {
+        Err(Error::new(ErrorKind::Other, "sandboxing requires Linux"))
+    }
+}
+
+// Allow the listed system calls and unix sockets, which syslog(3) may
+// reconnect, fail any other with EPERM, and kill the process if it makes
+// a call through a different ABI.
+pub fn filter(arch: u32, socket: u32, allowed: &[u32]) -> Vec<Insn> {
+    assert!(allowed.len() + 1 < 256, "too many system calls for a BPF jump");
+
+    let n = allowed.len();
+    let mut prog = vec![
+        stmt(BPF_LD_W_ABS, ARCH_OFFSET),
+        jump(arch, 1, 0),
+        stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+        stmt(BPF_LD_W_ABS, NR_OFFSET),
+        jump(socket, 0, 2),
+        stmt(BPF_LD_W_ABS, ARG0_OFFSET),
+        jump(AF_UNIX, (n + 1) as u8, n as u8),
+    ];
+    for (i, nr) in allowed.iter().enumerate() {
+        prog.push(jump(*nr, (n - i) as u8, 0));
+    }
+    prog.push(stmt(BPF_RET_K, SECCOMP_RET_ERRNO | EPERM));
+    prog.push(stmt(BPF_RET_K, SECCOMP_RET_ALLOW));
+    prog
+}
+
+// Fork a child that installs the seccomp filter and opens an IPv4 socket,
+// which the filter must refuse with EPERM.
+#[cfg(target_os = "linux")]
+pub fn check() -> Result<(), Error> {
+    let (arch, socket, allowed) = try!(arch());
+    let filter = filter(arch, socket, allowed);
+
+    let status = match try!(fork()) {
+        0   => unsafe { ffi::exit(ffi::refused(&filter)) },
+        pid => try!(wait(pid)),
+    };
+
+    match (status & 0x7f, (status >> 8) & 0xff) {
+        (0, 0)      => Ok(()),
+        (0, 1)      => Err(Error::new(ErrorKind::Other, "socket(2) was allowed")),
+        (0, 2)      => Err(Error::new(ErrorKind::Other, "failed to install the seccomp filter")),
+        (0, code)   => Err(Error::new(ErrorKind::Other, format!("check exited with status {}", code))),
+        (signal, _) => Err(Error::new(ErrorKind::Other, format!("check killed by signal {}", signal))),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn check() -> Result<(), Error> {
+    Err(Error::new(ErrorKind::Other, "sandboxing requires Linux"))
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn arch() -> Result<(u32, u32, &'static [u32]), Error> {
+    Ok((syscalls::x86_64::AUDIT_ARCH, syscalls::x86_64::SOCKET, syscalls::x86_64::ALLOWED))
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn arch() -> Result<(u32, u32, &'static [u32]), Error> {
+    Ok((syscalls::aarch64::AUDIT_ARCH, syscalls::aarch64::SOCKET, syscalls::aarch64::ALLOWED))
+}
+
+#[cfg(all(target_os = "linux", not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn arch() -> Result<(u32, u32, &'static [u32]), Error> {
+    Err(Error::new(ErrorKind::Other, "no system call list for this architecture"))
+}
+
+fn dir(file: &Path) -> PathBuf {
+    match file.parent() {
+        Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+        _                                 => PathBuf::from("."),
+    }
+}
+
+fn stmt(code: u16, k: u32) -> Insn {
+    Insn { code: code, jt: 0, jf: 0, k: k }
+}
+
+fn jump(k: u32, jt: u8, jf: u8) -> Insn {
+    Insn { code: BPF_JEQ_K, jt: jt, jf: jf, k: k }
+}
+
+#[cfg(test)]
+mod tests;
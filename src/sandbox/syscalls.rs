@@ -0,0 +1,137 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+// System calls the main loop needs once started: reading and reopening
+// logs, watching them with inotify, netlink and syslog messages, atomic
+// rewrites of the state and table files keeping their mode and owner,
+// and whatever the allocator and runtime use. Anything else fails with
+// EPERM, except socket which is allowed for AF_UNIX only so syslog can
+// reconnect.
+
+pub mod x86_64 {
+    pub const AUDIT_ARCH: u32 = 0xc000003e;
+    pub const SOCKET:     u32 = 41;
+
+    pub const ALLOWED: &'static [u32] = &[
+        0,   // read
+        1,   // write
+        2,   // open
+        3,   // close
+        4,   // stat
+        5,   // fstat
+        7,   // poll
+        8,   // lseek
+        9,   // mmap
+        10,  // mprotect
+        11,  // munmap
+        12,  // brk
+        13,  // rt_sigaction
+        14,  // rt_sigprocmask
+        15,  // rt_sigreturn
+        16,  // ioctl
+        17,  // pread64
+        19,  // readv
+        20,  // writev
+        23,  // select
+        24,  // sched_yield
+        25,  // mremap
+        28,  // madvise
+        35,  // nanosleep
+        39,  // getpid
+        42,  // connect
+        44,  // sendto
+        45,  // recvfrom
+        46,  // sendmsg
+        47,  // recvmsg
+        60,  // exit
+        72,  // fcntl
+        74,  // fsync
+        75,  // fdatasync
+        77,  // ftruncate
+        82,  // rename
+        87,  // unlink
+        91,  // fchmod
+        93,  // fchown
+        96,  // gettimeofday
+        131, // sigaltstack
+        201, // time
+        202, // futex
+        217, // getdents64
+        219, // restart_syscall
+        228, // clock_gettime
+        230, // clock_nanosleep
+        231, // exit_group
+        254, // inotify_add_watch
+        255, // inotify_rm_watch
+        257, // openat
+        262, // newfstatat
+        263, // unlinkat
+        264, // renameat
+        270, // pselect6
+        271, // ppoll
+        294, // inotify_init1
+        316, // renameat2
+        318, // getrandom
+        332, // statx
+    ];
+}
+
+pub mod aarch64 {
+    pub const AUDIT_ARCH: u32 = 0xc00000b7;
+    pub const SOCKET:     u32 = 198;
+
+    pub const ALLOWED: &'static [u32] = &[
+        25,  // fcntl
+        26,  // inotify_init1
+        27,  // inotify_add_watch
+        28,  // inotify_rm_watch
+        29,  // ioctl
+        35,  // unlinkat
+        38,  // renameat
+        46,  // ftruncate
+        52,  // fchmod
+        55,  // fchown
+        56,  // openat
+        57,  // close
+        61,  // getdents64
+        62,  // lseek
+        63,  // read
+        64,  // write
+        65,  // readv
+        66,  // writev
+        67,  // pread64
+        72,  // pselect6
+        73,  // ppoll
+        79,  // newfstatat
+        80,  // fstat
+        82,  // fsync
+        83,  // fdatasync
+        93,  // exit
+        94,  // exit_group
+        98,  // futex
+        101, // nanosleep
+        113, // clock_gettime
+        115, // clock_nanosleep
+        124, // sched_yield
+        128, // restart_syscall
+        132, // sigaltstack
+        134, // rt_sigaction
+        135, // rt_sigprocmask
+        139, // rt_sigreturn
+        169, // gettimeofday
+        172, // getpid
+        203, // connect
+        206, // sendto
+        207, // recvfrom
+        211, // sendmsg
+        212, // recvmsg
+        214, // brk
+        215, // munmap
+        216, // mremap
+        222, // mmap
+        226, // mprotect
+        233, // madvise
+        276, // renameat2
+        278, // getrandom
+        291, // statx
+    ];
+}
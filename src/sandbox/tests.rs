@@ -0,0 +1,102 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::path::{Path, PathBuf};
+use super::*;
+use super::syscalls::{aarch64, x86_64};
+
+#[test]
+fn filter_program() {
+    let prog = filter(x86_64::AUDIT_ARCH, x86_64::SOCKET, &[0, 1, 60]);
+    assert_eq!(vec![
+        Insn { code: 0x20, jt: 0, jf: 0, k: 4 },
+        Insn { code: 0x15, jt: 1, jf: 0, k: 0xc000003e },
+        Insn { code: 0x06, jt: 0, jf: 0, k: SECCOMP_RET_KILL_PROCESS },
+        Insn { code: 0x20, jt: 0, jf: 0, k: 0 },
+        Insn { code: 0x15, jt: 0, jf: 2, k: 41 },
+        Insn { code: 0x20, jt: 0, jf: 0, k: 16 },
+        Insn { code: 0x15, jt: 4, jf: 3, k: 1 },
+        Insn { code: 0x15, jt: 3, jf: 0, k: 0 },
+        Insn { code: 0x15, jt: 2, jf: 0, k: 1 },
+        Insn { code: 0x15, jt: 1, jf: 0, k: 60 },
+        Insn { code: 0x06, jt: 0, jf: 0, k: SECCOMP_RET_ERRNO | 1 },
+        Insn { code: 0x06, jt: 0, jf: 0, k: SECCOMP_RET_ALLOW },
+    ], prog);
+}
+
+#[test]
+fn filter_jumps_to_allow() {
+    for allowed in &[x86_64::ALLOWED, aarch64::ALLOWED] {
+        let prog = filter(0, 0, allowed);
+        let allow = prog.len() - 1;
+        assert_eq!(allow,     6 + prog[6].jt as usize + 1);
+        assert_eq!(allow - 1, 6 + prog[6].jf as usize + 1);
+        for (n, insn) in prog.iter().enumerate().skip(7).take(allowed.len()) {
+            assert_eq!(allow, n + insn.jt as usize + 1);
+        }
+    }
+}
+
+#[test]
+fn allowlists() {
+    // socket is only allowed for AF_UNIX, execve not at all
+    let forbidden = [(x86_64::ALLOWED, [x86_64::SOCKET, 59]), (aarch64::ALLOWED, [aarch64::SOCKET, 221])];
+    for &(allowed, forbidden) in &forbidden {
+        assert!(allowed.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(forbidden.iter().all(|nr| !allowed.contains(nr)));
+    }
+}
+
+#[test]
+fn sandbox_dirs() {
+    let mut sandbox = Sandbox::new();
+    sandbox.read(Path::new("/var/log/auth.log"));
+    sandbox.write(Path::new("/var/db/irongate.state"));
+    sandbox.write(Path::new("irongate.state"));
+
+    let (read, write) = sandbox.dirs();
+    assert_eq!(&[PathBuf::from("/var/log")][..], read);
+    assert_eq!(&[PathBuf::from("/var/db"), PathBuf::from(".")][..], write);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn forbidden_syscall() {
+    check().unwrap();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn rewrite_existing() {
+    use std::fs::{self, File};
+    use std::io::Read;
+    use std::os::unix::fs::PermissionsExt;
+    use tempdir::TempDir;
+    use privsep::{fork, wait};
+    use store::write_atomic;
+    use super::{arch, ffi};
+
+    let dir  = TempDir::new("test").unwrap();
+    let path = dir.path().join("irongate.state");
+    write_atomic(&path, b"old").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let (arch, socket, allowed) = arch().unwrap();
+    let filter = filter(arch, socket, allowed);
+
+    let status = match fork().unwrap() {
+        0 => unsafe {
+            let ok = ffi::no_new_privs().is_ok() && ffi::seccomp(&filter).is_ok();
+            ffi::exit(match ok && write_atomic(&path, b"new").is_ok() {
+                true  => 0,
+                false => 1,
+            })
+        },
+        pid => wait(pid).unwrap(),
+    };
+    assert_eq!(0, status);
+
+    let mut data = String::new();
+    File::open(&path).unwrap().read_to_string(&mut data).unwrap();
+    assert_eq!("new", data);
+    assert_eq!(0o600, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+}
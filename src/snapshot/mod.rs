@@ -0,0 +1,71 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Duration, UTC};
+
+use net::Cidr;
+use store::write_atomic;
+
+// a steady stream of changes still gets written after this many delays
+const MAX_WAIT: i32 = 10;
+
+// Writes the ban list as a pf table file, suitable for loading with
+// `table <name> persist file "..."` or `pfctl -T replace -f`. Changes
+// are debounced so a burst of bans results in a single write once no
+// change has been seen for `delay`.
+pub struct Snapshot {
+    path:  PathBuf,
+    delay: Duration,
+    dirty: Option<(DateTime<UTC>, DateTime<UTC>)>,
+}
+
+impl Snapshot {
+    pub fn new<P: AsRef<Path>>(path: P, delay: Duration) -> Snapshot {
+        Snapshot {
+            path:  path.as_ref().to_path_buf(),
+            delay: delay,
+            dirty: None,
+        }
+    }
+
+    pub fn changed(&mut self, now: DateTime<UTC>) {
+        self.dirty = match self.dirty {
+            Some((first, _)) => Some((first, now)),
+            None             => Some((now, now)),
+        };
+    }
+
+    // Whether flush would write, so callers can skip collecting entries.
+    pub fn due(&self, now: DateTime<UTC>) -> bool {
+        match self.dirty {
+            Some((first, last)) => now - last >= self.delay || now - first >= self.delay * MAX_WAIT,
+            None                => false,
+        }
+    }
+
+    pub fn flush(&mut self, now: DateTime<UTC>, entries: &[Cidr]) -> Result<bool, Error> {
+        if !self.due(now) {
+            return Ok(false);
+        }
+        try!(self.write(entries));
+        self.dirty = None;
+        Ok(true)
+    }
+
+    pub fn write(&self, entries: &[Cidr]) -> Result<(), Error> {
+        let mut lines: Vec<String> = entries.iter().map(|cidr| cidr.to_string()).collect();
+        lines.sort();
+
+        let mut text = String::from("# generated by irongate\n");
+        for line in &lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        write_atomic(&self.path, text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests;
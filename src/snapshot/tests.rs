@@ -0,0 +1,80 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fs::{self, File};
+use std::io::Read;
+use chrono::{Duration, UTC};
+use tempdir::TempDir;
+
+use net::Cidr;
+use super::Snapshot;
+
+#[test]
+fn debounce() {
+    let dir  = TempDir::new("test").unwrap();
+    let path = dir.path().join("pf.bruteforce");
+    let now  = UTC::now();
+    let mut snapshot = Snapshot::new(&path, Duration::seconds(5));
+
+    assert!(!snapshot.flush(now, &[]).unwrap());
+
+    snapshot.changed(now);
+    snapshot.changed(now + Duration::seconds(3));
+    assert!(!snapshot.due(now + Duration::seconds(5)));
+    assert!(!snapshot.flush(now + Duration::seconds(5), &[]).unwrap());
+    assert!(!path.exists());
+
+    assert!(snapshot.due(now + Duration::seconds(8)));
+    assert!(snapshot.flush(now + Duration::seconds(8), &[]).unwrap());
+    assert!(!snapshot.due(now + Duration::seconds(20)));
+    assert!(path.exists());
+    assert!(!snapshot.flush(now + Duration::seconds(20), &[]).unwrap());
+}
+
+#[test]
+fn max_wait() {
+    let dir  = TempDir::new("test").unwrap();
+    let path = dir.path().join("pf.bruteforce");
+    let now  = UTC::now();
+    let mut snapshot = Snapshot::new(&path, Duration::seconds(5));
+
+    for n in 0..10 {
+        snapshot.changed(now + Duration::seconds(n * 4));
+        assert!(!snapshot.flush(now + Duration::seconds(n * 4 + 1), &[]).unwrap());
+    }
+
+    snapshot.changed(now + Duration::seconds(49));
+    assert!(snapshot.flush(now + Duration::seconds(50), &[]).unwrap());
+}
+
+#[test]
+fn retry_failed() {
+    let dir  = TempDir::new("test").unwrap();
+    let path = dir.path().join("missing").join("pf.bruteforce");
+    let now  = UTC::now();
+    let mut snapshot = Snapshot::new(&path, Duration::seconds(5));
+
+    snapshot.changed(now);
+    assert!(snapshot.flush(now + Duration::seconds(5), &[]).is_err());
+
+    fs::create_dir(dir.path().join("missing")).unwrap();
+    assert!(snapshot.flush(now + Duration::seconds(6), &[]).unwrap());
+    assert!(path.exists());
+}
+
+#[test]
+fn format() {
+    let dir  = TempDir::new("test").unwrap();
+    let path = dir.path().join("pf.bruteforce");
+    let snapshot = Snapshot::new(&path, Duration::seconds(0));
+
+    let entries: Vec<Cidr> = vec![
+        "203.0.113.0/24".parse().unwrap(),
+        "2001:db8::/48".parse().unwrap(),
+        "198.51.100.7".parse().unwrap(),
+    ];
+    snapshot.write(&entries).unwrap();
+
+    let mut text = String::new();
+    File::open(&path).unwrap().read_to_string(&mut text).unwrap();
+    assert_eq!("# generated by irongate\n198.51.100.7\n2001:db8::/48\n203.0.113.0/24\n", text);
+}
@@ -0,0 +1,187 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use chrono::{TimeZone, UTC};
+use rustc_serialize::json::{self, Json};
+
+use libc::types::os::arch::c95::c_int;
+use libc::types::os::arch::posix88::{gid_t, mode_t, uid_t};
+
+use ban::{Bans, Offense};
+use net::Cidr;
+
+pub const VERSION: u32 = 1;
+
+#[derive(RustcEncodable, RustcDecodable, PartialEq, Debug)]
+pub struct State {
+    pub version:  u32,
+    pub bans:     Vec<BanRecord>,
+    pub offenses: Vec<OffenseRecord>,
+    pub evicted:  Option<usize>,
+}
+
+#[derive(RustcEncodable, RustcDecodable, PartialEq, Debug)]
+pub struct BanRecord {
+    pub addr:    String,
+    pub started: Option<i64>,
+    pub expires: Option<i64>,
+}
+
+#[derive(RustcEncodable, RustcDecodable, PartialEq, Debug)]
+pub struct OffenseRecord {
+    pub addr:  String,
+    pub count: u32,
+    pub last:  i64,
+}
+
+impl State {
+    pub fn capture(bans: &Bans) -> State {
+        State {
+            version:  VERSION,
+            bans:     bans.entries().iter().map(|&(cidr, expires)| BanRecord {
+                addr:    cidr.to_string(),
+                started: bans.started(&cidr).map(|t| t.timestamp()),
+                expires: expires.map(|t| t.timestamp()),
+            }).collect(),
+            offenses: bans.history().iter().map(|&(cidr, offense)| OffenseRecord {
+                addr:  cidr.to_string(),
+                count: offense.count,
+                last:  offense.last.timestamp(),
+            }).collect(),
+            evicted:  None,
+        }
+    }
+
+    pub fn restore(&self, bans: &mut Bans) {
+        for record in &self.bans {
+            if let Ok(cidr) = record.addr.parse::<Cidr>() {
+                bans.schedule(cidr, record.expires.map(|t| UTC.timestamp(t, 0)));
+                if let Some(t) = record.started {
+                    bans.start(cidr, UTC.timestamp(t, 0));
+                }
+            }
+        }
+
+        for record in &self.offenses {
+            if let Ok(cidr) = record.addr.parse::<Cidr>() {
+                bans.record(cidr, Offense {
+                    count: record.count,
+                    last:  UTC.timestamp(record.last, 0),
+                });
+            }
+        }
+    }
+}
+
+pub struct Store {
+    path: PathBuf,
+}
+
+impl Store {
+    pub fn new<P: AsRef<Path>>(path: P) -> Store {
+        Store { path: path.as_ref().to_path_buf() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> Result<Option<State>, Error> {
+        let mut file = match File::open(&self.path) {
+            Ok(file)                                      => file,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e)                                        => return Err(e),
+        };
+
+        let mut data = String::new();
+        try!(file.read_to_string(&mut data));
+
+        let version = Json::from_str(&data).ok().and_then(|json| {
+            json.find("version").and_then(|version| version.as_u64())
+        });
+        if let Some(version) = version {
+            if version != VERSION as u64 {
+                let msg = format!("unsupported state file version {}", version);
+                return Err(Error::new(ErrorKind::InvalidInput, msg));
+            }
+        }
+
+        match json::decode(&data) {
+            Ok(state) => Ok(Some(state)),
+            Err(..)   => {
+                let aside = self.corrupt_path();
+                try!(fs::rename(&self.path, &aside));
+                let msg = format!("corrupt state file moved to {}", aside.display());
+                Err(Error::new(ErrorKind::InvalidData, msg))
+            },
+        }
+    }
+
+    pub fn corrupt_path(&self) -> PathBuf {
+        let mut path = self.path.as_os_str().to_os_string();
+        path.push(".corrupt");
+        PathBuf::from(path)
+    }
+
+    pub fn save(&self, state: &State) -> Result<(), Error> {
+        let data = match json::encode(state) {
+            Ok(data) => data,
+            Err(..)  => return Err(Error::new(ErrorKind::InvalidInput, "failed to encode state")),
+        };
+        write_atomic(&self.path, data.as_bytes())
+    }
+}
+
+extern {
+    fn fchmod(fd: c_int, mode: mode_t) -> c_int;
+    fn fchown(fd: c_int, owner: uid_t, group: gid_t) -> c_int;
+}
+
+// Replace `path` with `data` via a temporary file and a rename, keeping
+// the mode and owner of the file being replaced.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let mut tmp = path.as_os_str().to_os_string();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    {
+        let mut file = try!(OpenOptions::new().write(true).create(true).truncate(true).open(&tmp));
+        if let Ok(meta) = fs::metadata(path) {
+            let own = try!(file.metadata());
+            let fd  = file.as_raw_fd();
+            if own.mode() & 0o7777 != meta.mode() & 0o7777 {
+                try!(check(unsafe { fchmod(fd, (meta.mode() & 0o7777) as mode_t) }));
+            }
+            if (own.uid(), own.gid()) != (meta.uid(), meta.gid()) {
+                try!(check(unsafe { fchown(fd, meta.uid() as uid_t, meta.gid() as gid_t) }));
+            }
+        }
+        try!(file.write_all(data));
+        try!(file.sync_all());
+    }
+
+    try!(fs::rename(&tmp, path));
+
+    let dir = match path.parent() {
+        Some(dir) if dir.as_os_str().len() > 0 => dir,
+        _                                      => Path::new("."),
+    };
+    try!(try!(File::open(dir)).sync_all());
+
+    Ok(())
+}
+
+fn check(rc: c_int) -> Result<(), Error> {
+    match rc {
+        -1 => Err(Error::last_os_error()),
+        _  => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,85 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::fs::File;
+use std::io::{ErrorKind, Write};
+use chrono::{Duration, TimeZone, UTC};
+use tempdir::TempDir;
+use ban::Bans;
+use net::Cidr;
+use super::*;
+
+#[test]
+fn load_missing() {
+    let dir = TempDir::new("test").unwrap();
+    let store = Store::new(dir.path().join("state.json"));
+    assert_eq!(None, store.load().unwrap());
+}
+
+#[test]
+fn save_load() {
+    let dir = TempDir::new("test").unwrap();
+    let store = Store::new(dir.path().join("state.json"));
+
+    let state = State {
+        version:  VERSION,
+        bans:     vec![BanRecord { addr: "203.0.113.0/24".to_string(), started: Some(1431990000), expires: Some(1431993600) }],
+        offenses: vec![OffenseRecord { addr: "203.0.113.0/24".to_string(), count: 2, last: 1431990000 }],
+        evicted:  Some(3),
+    };
+
+    store.save(&state).unwrap();
+    assert_eq!(Some(state), store.load().unwrap());
+    assert!(!dir.path().join("state.json.tmp").exists());
+}
+
+#[test]
+fn load_invalid() {
+    let dir = TempDir::new("test").unwrap();
+    let path = dir.path().join("state.json");
+    let store = Store::new(&path);
+
+    write!(&mut File::create(&path).unwrap(), "{{\"version\":").unwrap();
+    assert_eq!(ErrorKind::InvalidData, store.load().unwrap_err().kind());
+    assert!(!path.exists() && store.corrupt_path().exists());
+    assert_eq!(None, store.load().unwrap());
+
+    write!(&mut File::create(&path).unwrap(), "{{\"version\":99,\"bans\":[],\"offenses\":[]}}").unwrap();
+    assert_eq!(ErrorKind::InvalidInput, store.load().unwrap_err().kind());
+    assert!(path.exists());
+
+    write!(&mut File::create(&path).unwrap(), "{{\"version\":99,\"bans\":{{\"new\":1}}}}").unwrap();
+    assert_eq!(ErrorKind::InvalidInput, store.load().unwrap_err().kind());
+}
+
+#[test]
+fn load_older_fields() {
+    let dir = TempDir::new("test").unwrap();
+    let path = dir.path().join("state.json");
+    let store = Store::new(&path);
+
+    write!(&mut File::create(&path).unwrap(), "{{\"version\":1,\"bans\":[{{\"addr\":\"192.0.2.1\",\"expires\":null}}],\"offenses\":[]}}").unwrap();
+    let state = store.load().unwrap().unwrap();
+    assert_eq!(None, state.bans[0].started);
+    assert_eq!(None, state.evicted);
+}
+
+#[test]
+fn capture_restore() {
+    let now  = UTC.timestamp(1431990000, 0);
+    let a    = Cidr::host("192.0.2.1".parse().unwrap());
+    let b    = Cidr::new("2001:db8::".parse().unwrap(), 64);
+    let mut bans = Bans::new(Duration::days(1));
+
+    bans.insert(a, now, Some(Duration::minutes(10)));
+    bans.insert(b, now, None);
+
+    let state = State::capture(&bans);
+    let mut restored = Bans::new(Duration::days(1));
+    state.restore(&mut restored);
+
+    assert_eq!(Some(Some(now + Duration::minutes(10))), restored.expires(&a));
+    assert_eq!(Some(None), restored.expires(&b));
+    assert_eq!(Some(now), restored.started(&a));
+    assert_eq!(1, restored.offenses(&a, now));
+    assert_eq!(1, restored.offenses(&b, now));
+}
@@ -24,7 +24,7 @@ impl Buf {
         }
     }
 
-    pub fn fill(&mut self, fd: c_int, len: usize) -> Result<(), Error> {
+    pub fn fill(&mut self, fd: c_int, len: usize) -> Result<usize, Error> {
         self.compact();
         self.bytes.reserve(len);
         unsafe {
@@ -35,8 +35,8 @@ impl Buf {
                 return Err(Error::last_os_error())
             }
             self.bytes.set_len(pos + n as usize);
+            Ok(n as usize)
         }
-        Ok(())
     }
 
     pub fn append(&mut self, bytes: &[u8]) {
@@ -0,0 +1,140 @@
+// Copyright (C) 2015 - Will Glozer.  All rights reserved.
+
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use libc::types::os::arch::c95::c_int;
+
+use inotify::*;
+use posix::Fd;
+use super::Error;
+use super::buf::Buf;
+
+const READ_LEN: usize = 65536;
+
+pub struct Tailer {
+    inotify: Inotify,
+    path:    PathBuf,
+    name:    OsString,
+    dir:     c_int,
+    file:    Fd,
+    watch:   c_int,
+    unread:  bool,
+    buf:     Buf,
+}
+
+impl Tailer {
+    pub fn new<S: AsRef<OsStr> + ?Sized>(path: &S) -> Result<Tailer, Error> {
+        let mut inotify = try!(Inotify::new());
+        let path = PathBuf::from(path);
+        let name = path.file_name().unwrap().to_os_string();
+
+        let dir = match path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _                                 => Path::new("."),
+        };
+        let dir = try!(inotify.add_watch(dir.as_os_str(), IN_CREATE | IN_MOVED_TO));
+
+        let mut tailer = Tailer {
+            inotify: inotify,
+            path:    path,
+            name:    name,
+            dir:     dir,
+            file:    Fd(-1),
+            watch:   -1,
+            unread:  false,
+            buf:     Buf::new(b'\n'),
+        };
+        try!(tailer.open());
+        Ok(tailer)
+    }
+
+    pub fn next_line<'a>(&'a mut self, timeout: Option<Duration>) -> Result<Option<&'a str>, Error> {
+        match self.buf.next_line() {
+            Ok(Some(str)) => Ok(Some(str)),
+            _             => self.wait(timeout),
+        }
+    }
+
+    // A newly opened file is read on the following call rather than
+    // waiting for it to change, like a kqueue read event would be.
+    pub fn wait<'a>(&'a mut self, timeout: Option<Duration>) -> Result<Option<&'a str>, Error> {
+        if self.unread {
+            self.unread = false;
+            try!(self.read());
+            return Ok(try!(self.buf.next_line()));
+        }
+
+        let mut buf = [0u8; 4096];
+        let n = try!(self.inotify.wait(&mut buf, timeout));
+        for event in try!(events(&buf[..n])) {
+            if event.wd == self.watch && event.mask.intersects(IN_DELETE_SELF | IN_MOVE_SELF) {
+                try!(self.read());
+                try!(self.close());
+                try!(self.open());
+            } else if event.wd == self.watch && event.mask.contains(IN_MODIFY) {
+                try!(self.read());
+            } else if event.wd == self.dir && self.file.0 == -1 && event.name == self.name.as_bytes() {
+                try!(self.open());
+            }
+        }
+        Ok(try!(self.buf.next_line()))
+    }
+
+    // Read to the end of the file, starting over if it was truncated.
+    fn read(&mut self) -> Result<(), io::Error> {
+        if self.file.0 == -1 {
+            return Ok(())
+        }
+
+        let pos = try!(self.file.position());
+        if try!(self.file.size()) < pos {
+            try!(self.file.seek(-pos));
+        }
+
+        while try!(self.buf.fill(self.file.0, READ_LEN)) > 0 {}
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<(), io::Error> {
+        let fd = match Fd::open(self.path.as_os_str()) {
+            Ok(fd) => fd,
+            Err(_) => return Ok(())
+        };
+
+        let mask = IN_MODIFY | IN_DELETE_SELF | IN_MOVE_SELF;
+        match self.inotify.add_watch(self.path.as_os_str(), mask) {
+            Ok(wd) => {
+                self.file   = fd;
+                self.watch  = wd;
+                self.unread = true;
+                Ok(())
+            },
+            Err(e) => {
+                try!(fd.close());
+                Err(e)
+            },
+        }
+    }
+
+    fn close(&mut self) -> Result<(), io::Error> {
+        if self.watch != -1 {
+            self.inotify.rm_watch(self.watch);
+            self.watch = -1;
+        }
+        if self.file.0 != -1 {
+            try!(self.file.close());
+            self.file = Fd(-1);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Tailer {
+    fn drop(&mut self) {
+        self.close().unwrap();
+    }
+}
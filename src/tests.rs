@@ -83,3 +83,621 @@ fn ip_addr() {
     assert_eq!(false, "10.0.0.1".parse::<Ipv4Addr>().unwrap().is_global());
     assert_eq!(false,  "::1".parse::<Ipv6Addr>().unwrap().is_global());
 }
+
+#[test]
+fn allowlist() {
+    let allow = super::allowlist("198.51.100.0/24, 2001:db8::7").unwrap();
+    assert_eq!(2, allow.len());
+    assert!(super::allowlist("").unwrap().is_empty());
+    assert!(super::allowlist("198.51.100.1/24").is_err());
+}
+
+#[test]
+fn table_flags() {
+    use pf::{PFR_TFLAG_PERSIST, PFR_TFLAG_COUNTERS};
+
+    assert_eq!(PFR_TFLAG_PERSIST | PFR_TFLAG_COUNTERS, super::table_flags("persist, counters").unwrap());
+    assert!(super::table_flags("").unwrap().is_empty());
+    assert!(super::table_flags("persist,sticky").is_err());
+}
+
+#[test]
+fn backend_pf() {
+    let name: String   = (0..32).map(|_| 'x').collect();
+    let anchor: String = (0..1024).map(|_| 'x').collect();
+    assert!(super::backend(&args(&["-B", "pf", "auth.log"])).is_ok());
+    assert!(super::backend(&args(&["-B", "pf", "-t", &name[1..], "auth.log"])).is_ok());
+    assert!(super::backend(&args(&["-B", "pf", "-t", &name, "auth.log"])).is_err());
+    assert!(super::backend(&args(&["-B", "pf", "--anchor", &anchor, "auth.log"])).is_err());
+}
+
+#[test]
+fn privsep_args() {
+    let privsep = |argv: &[&str]| super::privsep(&args(argv));
+    assert!(privsep(&["-u", "nobody", "/var/log/auth.log"]).unwrap().is_some());
+    assert!(privsep(&["-u", "nobody", "-e", "fewest-hits", "/var/log/auth.log"]).is_err());
+    assert!(privsep(&["-u", "nobody", "--state", "/var/log/irongate.json", "/var/log/auth.log"]).is_ok());
+    assert!(privsep(&["-u", "nobody", "--state", "/var/db/irongate.json", "/var/log/auth.log"]).is_err());
+    assert!(privsep(&["-u", "nobody", "-f", "/etc/pf.bruteforce", "/var/log/auth.log"]).is_err());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn sandbox_args() {
+    let sandbox = |argv: &[&str]| super::sandbox(&args(argv));
+    assert!(!sandbox(&["/var/log/auth.log"]).unwrap());
+    assert!(sandbox(&["--sandbox", "-B", "nft", "/var/log/auth.log"]).unwrap());
+    assert!(sandbox(&["--sandbox", "-B", "hosts-deny", "/var/log/auth.log"]).is_err());
+    assert!(sandbox(&["--sandbox", "-B", "hosts-deny", "-u", "nobody", "/var/log/auth.log"]).unwrap());
+    assert!(sandbox(&["--sandbox", "-B", "exec", "--exec-ban", "ban {addr}", "/var/log/auth.log"]).is_err());
+}
+
+#[test]
+fn backend_ipset() {
+    assert!(super::backend(&args(&["-B", "ipset", "--ipset-type", "hash:ip", "auth.log"])).is_ok());
+    assert!(super::backend(&args(&["-B", "ipset", "-L", "5", "auth.log"])).is_ok());
+    assert!(super::backend(&args(&["-B", "ipset", "--ipset-type", "hash:ip", "-L", "5", "auth.log"])).is_err());
+    assert!(super::backend(&args(&["-B", "ipset", "--ipset-type", "hash:ip", "-e", "collapse", "auth.log"])).is_err());
+}
+
+#[test]
+fn backend_exec() {
+    assert!(super::backend(&args(&["-B", "exec", "--exec-ban", "ban {addr}", "auth.log"])).is_ok());
+    assert!(super::backend(&args(&["-B", "exec", "--exec-ban", "ban {addr}", "-b", "10", "auth.log"])).is_err());
+    assert!(super::backend(&args(&["-B", "exec", "--exec-ban", "ban {addr}", "-m", "100", "auth.log"])).is_err());
+    assert!(super::backend(&args(&["-B", "exec", "--exec-ban", "ban {addr}", "--exec-unban", "unban {addr}",
+                                   "-b", "10", "auth.log"])).is_ok());
+}
+
+#[test]
+fn ban_times() {
+    use chrono::Duration;
+
+    let times = super::ban_times("10,60,1440,0").unwrap();
+    assert_eq!(vec![
+        Some(Duration::minutes(10)),
+        Some(Duration::minutes(60)),
+        Some(Duration::minutes(1440)),
+        None,
+    ], times);
+
+    assert_eq!(vec![None], super::ban_times("0").unwrap());
+    assert!(super::ban_times("10,,60").is_err());
+    assert!(super::ban_times("-5").is_err());
+}
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::thread;
+use chrono::{Duration, Local};
+use tempdir::TempDir;
+use allow::Allowlist;
+use ban::Offense;
+use block::{Blocker, Memory};
+use evict::Policy;
+use exec::{self, Exec};
+use lockout::Lockout;
+use net::Cidr;
+use pf;
+use privsep::{self, Client, Server};
+use rules::{Decision, Rule, Rules};
+use store::Store;
+use super::{Args, Backend, IronGate, Monitor, Pending};
+
+#[test]
+fn monitor_block_after_limit() {
+    let gate = gate();
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    let event = "sshd[92736]: Invalid user postgres from 193.107.17.72";
+
+    for _ in 0..3 {
+        monitor.line(&log(event)).unwrap();
+    }
+    assert_eq!(Some(&[][..]), monitor.blocker.table("irongate"));
+
+    monitor.line(&log(event)).unwrap();
+    assert_eq!(Some(&[cidr("193.107.17.72")][..]), monitor.blocker.table("irongate"));
+}
+
+#[test]
+fn monitor_block_prefix() {
+    let mut gate = gate();
+    gate.rules.prefix_limit = 3;
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+
+    for n in 1..5 {
+        let event = format!("sshd[92736]: Invalid user postgres from 193.107.17.{}", n);
+        monitor.line(&log(&event)).unwrap();
+    }
+
+    let net = Cidr::new("193.107.17.0".parse().unwrap(), 24);
+    assert_eq!(Some(&[net][..]), monitor.blocker.table("irongate"));
+}
+
+#[test]
+fn monitor_ignore_non_global() {
+    let gate = gate();
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+
+    for _ in 0..10 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 10.0.0.1")).unwrap();
+        monitor.line(&log("sshd[92368]: Did not receive identification string from 193.107.17.72")).unwrap();
+    }
+    assert_eq!(Some(&[][..]), monitor.blocker.table("irongate"));
+}
+
+#[test]
+fn monitor_expire() {
+    let gate = gate();
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+    assert_eq!(1, monitor.blocker.table("irongate").unwrap().len());
+
+    monitor.expire(UTC::now() + Duration::minutes(5)).unwrap();
+    assert_eq!(1, monitor.blocker.table("irongate").unwrap().len());
+
+    monitor.expire(UTC::now() + Duration::minutes(11)).unwrap();
+    assert_eq!(0, monitor.blocker.table("irongate").unwrap().len());
+}
+
+#[test]
+fn monitor_expire_present() {
+    let gate = gate();
+    let mut blocker = Memory::new();
+    blocker.ensure_table("irongate").unwrap();
+    blocker.add("irongate", &[cidr("193.107.17.72")], None).unwrap();
+    let mut monitor = Monitor::new(&gate, blocker).unwrap();
+
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+    assert!(monitor.bans.contains(&cidr("193.107.17.72")));
+
+    monitor.expire(UTC::now() + Duration::minutes(11)).unwrap();
+    assert_eq!(0, monitor.blocker.table("irongate").unwrap().len());
+}
+
+#[test]
+fn monitor_restore() {
+    let dir = TempDir::new("test").unwrap();
+    let mut gate = gate();
+    gate.store = Some(Store::new(dir.path().join("state.json")));
+
+    {
+        let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+        for _ in 0..4 {
+            monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+        }
+    }
+
+    let mut blocker = Memory::new();
+    blocker.ensure_table("irongate").unwrap();
+    blocker.add("irongate", &[cidr("8.254.73.28")], None).unwrap();
+
+    let monitor = Monitor::new(&gate, blocker).unwrap();
+    let table = monitor.blocker.table("irongate").unwrap();
+    assert_eq!(&[cidr("8.254.73.28"), cidr("193.107.17.72")][..], table);
+}
+
+#[test]
+fn monitor_resync() {
+    let dir = TempDir::new("test").unwrap();
+    let mut gate = gate();
+    gate.store  = Some(Store::new(dir.path().join("state.json")));
+    gate.resync = true;
+
+    {
+        let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+        for _ in 0..4 {
+            monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+        }
+    }
+
+    let mut blocker = Memory::new();
+    blocker.ensure_table("irongate").unwrap();
+    blocker.add("irongate", &[cidr("8.254.73.28")], None).unwrap();
+
+    let monitor = Monitor::new(&gate, blocker).unwrap();
+    assert_eq!(Some(&[cidr("193.107.17.72")][..]), monitor.blocker.table("irongate"));
+
+    let timeout = monitor.blocker.timeout(&cidr("193.107.17.72")).unwrap().unwrap();
+    assert!(timeout > Duration::minutes(9) && timeout <= Duration::minutes(10));
+}
+
+#[test]
+fn monitor_keep_active() {
+    let mut gate = gate();
+    gate.keep_active = 10;
+
+    let addr = cidr("193.107.17.72");
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+
+    monitor.blocker.hit(addr, 25);
+    monitor.expire(UTC::now() + Duration::minutes(11)).unwrap();
+    assert_eq!(Some(&[addr][..]), monitor.blocker.table("irongate"));
+
+    monitor.blocker.hit(addr, 5);
+    monitor.expire(UTC::now() + Duration::minutes(22)).unwrap();
+    assert_eq!(Some(&[][..]), monitor.blocker.table("irongate"));
+}
+
+#[test]
+fn monitor_recreate_table() {
+    let gate = gate();
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+
+    assert!(monitor.blocker.drop_table("irongate"));
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 8.254.73.28")).unwrap();
+    }
+
+    let table = monitor.blocker.table("irongate").unwrap();
+    assert_eq!(&[cidr("193.107.17.72"), cidr("8.254.73.28")][..], table);
+}
+
+#[test]
+fn monitor_batch() {
+    let mut gate = gate();
+    gate.batch_size  = 3;
+    gate.batch_delay = Duration::milliseconds(100);
+
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    for n in 1..3 {
+        let event = format!("sshd[92736]: Invalid user postgres from 193.107.17.{}", n);
+        for _ in 0..5 {
+            monitor.line(&log(&event)).unwrap();
+        }
+    }
+    assert_eq!(Some(&[][..]), monitor.blocker.table("irongate"));
+    assert_eq!(2, monitor.pending.len());
+
+    monitor.tick(UTC::now() + Duration::milliseconds(100)).unwrap();
+    assert_eq!(2, monitor.blocker.table("irongate").unwrap().len());
+    assert!(monitor.bans.contains(&cidr("193.107.17.2")));
+
+    for n in 3..6 {
+        let event = format!("sshd[92736]: Invalid user postgres from 193.107.17.{}", n);
+        for _ in 0..4 {
+            monitor.line(&log(&event)).unwrap();
+        }
+    }
+    assert_eq!(5, monitor.blocker.table("irongate").unwrap().len());
+    assert!(monitor.pending.is_empty());
+}
+
+#[test]
+fn monitor_evict_oldest() {
+    let mut gate = gate();
+    gate.max_entries = 2;
+
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    let now = UTC::now();
+    monitor.bans.insert(cidr("193.107.17.1"), now - Duration::minutes(2), None);
+    monitor.bans.insert(cidr("193.107.17.2"), now - Duration::minutes(1), None);
+    monitor.blocker.add("irongate", &[cidr("193.107.17.1"), cidr("193.107.17.2")], None).unwrap();
+
+    let event = "sshd[92736]: Invalid user postgres from 8.254.73.28";
+    for _ in 0..4 {
+        monitor.line(&log(event)).unwrap();
+    }
+
+    let table = monitor.blocker.table("irongate").unwrap();
+    assert_eq!(&[cidr("193.107.17.2"), cidr("8.254.73.28")][..], table);
+    assert!(!monitor.bans.contains(&cidr("193.107.17.1")));
+    assert_eq!(1, monitor.evicted);
+    assert!(monitor.near);
+}
+
+#[test]
+fn monitor_evict_present() {
+    let mut gate = gate();
+    gate.max_entries = 2;
+
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    let now = UTC::now();
+    monitor.bans.insert(cidr("193.107.17.1"), now - Duration::minutes(1), None);
+    monitor.bans.insert(cidr("193.107.17.2"), now - Duration::minutes(2), None);
+    monitor.bans.record(cidr("193.107.17.1"), Offense { count: 1, last: now - Duration::minutes(5) });
+
+    let pending = |addr| Pending {
+        decision: Decision { cidr: cidr(addr), count: 4, rule: Rule::Host(3) },
+        level:    1,
+        ban:      None,
+    };
+
+    monitor.make_room(&[pending("193.107.17.1")]).unwrap();
+    assert_eq!(0, monitor.evicted);
+
+    // the oldest ban is evicted, not the oldest offense
+    monitor.make_room(&[pending("8.254.73.28")]).unwrap();
+    assert_eq!(1, monitor.evicted);
+    assert!(monitor.bans.contains(&cidr("193.107.17.1")));
+    assert!(!monitor.bans.contains(&cidr("193.107.17.2")));
+}
+
+#[test]
+fn monitor_evict_collapse() {
+    let mut gate = gate();
+    gate.max_entries = 2;
+    gate.evict       = Policy::Collapse;
+
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    let now = UTC::now();
+    monitor.bans.insert(cidr("193.107.17.1"), now, Some(Duration::minutes(10)));
+    monitor.bans.insert(cidr("193.107.17.2"), now, Some(Duration::minutes(20)));
+    monitor.blocker.add("irongate", &[cidr("193.107.17.1"), cidr("193.107.17.2")], None).unwrap();
+
+    let event = "sshd[92736]: Invalid user postgres from 8.254.73.28";
+    for _ in 0..4 {
+        monitor.line(&log(event)).unwrap();
+    }
+
+    let prefix = "193.107.17.0/24".parse().unwrap();
+    let table  = monitor.blocker.table("irongate").unwrap();
+    assert_eq!(&[prefix, cidr("8.254.73.28")][..], table);
+    assert_eq!(Some(Some(now + Duration::minutes(20))), monitor.bans.expires(&prefix));
+    assert!(!monitor.bans.contains(&cidr("193.107.17.1")));
+}
+
+#[test]
+fn monitor_failed_ban() {
+    let gate = gate();
+    let exec = Exec::new(exec::Config {
+        ban:       "false {addr}".to_string(),
+        unban:     Some("true {addr}".to_string()),
+        list:      None,
+        timeout:   1000,
+        retries:   0,
+        backoff:   1,
+        jobs:      1,
+        unchanged: None,
+    }).unwrap();
+    let mut monitor = Monitor::new(&gate, exec).unwrap();
+
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+    assert!(!monitor.bans.contains(&cidr("193.107.17.72")));
+}
+
+#[test]
+fn monitor_table_file() {
+    let dir = TempDir::new("test").unwrap();
+    let path = dir.path().join("pf.bruteforce");
+    let mut gate = gate();
+    gate.table_file = Some((path.display().to_string(), Duration::seconds(5)));
+
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+
+    monitor.expire(UTC::now()).unwrap();
+    assert!(!path.exists());
+    monitor.expire(UTC::now() + Duration::seconds(5)).unwrap();
+
+    let mut text = String::new();
+    File::open(&path).unwrap().read_to_string(&mut text).unwrap();
+    assert_eq!("# generated by irongate\n193.107.17.72\n", text);
+}
+
+#[test]
+fn monitor_dry_run() {
+    let dir = TempDir::new("test").unwrap();
+    let path = dir.path().join("state.json");
+    let mut gate = gate();
+    gate.dry_run = true;
+    gate.store = Some(Store::new(&path));
+
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+
+    assert!(monitor.bans.contains(&cidr("193.107.17.72")));
+    assert!(!path.exists());
+}
+
+#[test]
+fn monitor_shadow() {
+    let mut gate = gate();
+    gate.shadow = Some(Rules { limit: 1, ..gate.rules });
+
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    for _ in 0..3 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+
+    let reported = monitor.shadowed[&cidr("193.107.17.72")];
+    monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    assert_eq!(reported, monitor.shadowed[&cidr("193.107.17.72")]);
+    assert_eq!(Some(&[][..]), monitor.blocker.table("irongate"));
+
+    monitor.expire(UTC::now()).unwrap();
+    assert!(!monitor.shadowed.is_empty());
+    monitor.expire(UTC::now() + Duration::minutes(2)).unwrap();
+    assert!(monitor.shadowed.is_empty());
+}
+
+#[test]
+fn monitor_allow() {
+    let mut gate = gate();
+    gate.rules.prefix_limit = 3;
+    gate.allow.insert(cidr("193.107.17.72"));
+
+    let mut monitor = Monitor::new(&gate, Memory::new()).unwrap();
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    }
+    for n in 1..5 {
+        let event = format!("sshd[92736]: Invalid user postgres from 193.107.17.{}", n);
+        monitor.line(&log(&event)).unwrap();
+    }
+
+    assert_eq!(Some(&[][..]), monitor.blocker.table("irongate"));
+}
+
+#[test]
+fn monitor_lockout() {
+    let gate = gate();
+    let mut lockout = Lockout::new();
+    lockout.add_gateway("193.107.17.1".parse().unwrap());
+
+    let mut monitor = Monitor::with_lockout(&gate, Memory::new(), lockout).unwrap();
+    monitor.line(&log("sshd[4211]: Accepted publickey for will from 193.107.17.72 port 52344 ssh2")).unwrap();
+    for _ in 0..4 {
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+        monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.1")).unwrap();
+    }
+    assert_eq!(Some(&[][..]), monitor.blocker.table("irongate"));
+
+    monitor.line(&log("sshd[4211]: pam_unix(sshd:session): session closed for user will")).unwrap();
+    monitor.line(&log("sshd[92736]: Invalid user postgres from 193.107.17.72")).unwrap();
+    assert_eq!(Some(&[cidr("193.107.17.72")][..]), monitor.blocker.table("irongate"));
+}
+
+#[test]
+fn monitor_privsep() {
+    let (parent, child) = privsep::socketpair().unwrap();
+    let server = thread::spawn(move || {
+        let mut blocker = Memory::new();
+        blocker.ensure_table("irongate").unwrap();
+        gate().serve(&mut blocker, Server::new(parent), &Lockout::new()).unwrap();
+        blocker
+    });
+
+    {
+        let gate = gate();
+        let mut monitor = Monitor::new(&gate, Client::new(child)).unwrap();
+        for n in 1..3 {
+            let event = format!("sshd[92736]: Invalid user postgres from 193.107.17.{}", n);
+            for _ in 0..4 {
+                monitor.line(&log(&event)).unwrap();
+            }
+        }
+        monitor.expire(UTC::now() + Duration::minutes(11)).unwrap();
+        assert_eq!(0, monitor.bans.len());
+
+        let event = "sshd[92736]: Invalid user postgres from 8.254.73.28";
+        for _ in 0..4 {
+            monitor.line(&log(event)).unwrap();
+        }
+    }
+
+    let blocker = server.join().unwrap();
+    assert_eq!(Some(&[cidr("8.254.73.28")][..]), blocker.table("irongate"));
+}
+
+#[test]
+fn privsep_refuse() {
+    let (parent, child) = privsep::socketpair().unwrap();
+    let server = thread::spawn(move || {
+        let mut gate = gate();
+        gate.allow.insert("193.107.17.0/24".parse().unwrap());
+
+        let mut lockout = Lockout::new();
+        lockout.add_gateway("8.8.8.8".parse().unwrap());
+
+        let mut blocker = Memory::new();
+        blocker.ensure_table("irongate").unwrap();
+        gate.serve(&mut blocker, Server::new(parent), &lockout).unwrap();
+        blocker
+    });
+
+    {
+        let mut client = Client::new(child);
+        assert_eq!(0, client.add("irongate", &[cidr("193.107.17.72")], None).unwrap());
+        assert_eq!(0, client.add("irongate", &[cidr("10.0.0.1")], None).unwrap());
+        assert_eq!(0, client.add("irongate", &[cidr("8.8.8.8")], None).unwrap());
+        assert_eq!(0, client.add("irongate", &["8.254.0.0/16".parse().unwrap()], None).unwrap());
+        assert_eq!(1, client.add("irongate", &[cidr("8.254.73.28")], None).unwrap());
+    }
+
+    let blocker = server.join().unwrap();
+    assert_eq!(Some(&[cidr("8.254.73.28")][..]), blocker.table("irongate"));
+}
+
+#[test]
+fn privsep_recreate_table() {
+    let (parent, child) = privsep::socketpair().unwrap();
+    let server = thread::spawn(move || {
+        let mut blocker = Memory::new();
+        gate().serve(&mut blocker, Server::new(parent), &Lockout::new()).unwrap();
+        blocker
+    });
+
+    {
+        let mut client = Client::new(child);
+        let e = client.add("irongate", &[cidr("8.254.73.28")], None).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, e.kind());
+        assert_eq!(1, client.add("irongate", &[cidr("8.254.73.28")], None).unwrap());
+    }
+
+    let blocker = server.join().unwrap();
+    assert_eq!(Some(&[cidr("8.254.73.28")][..]), blocker.table("irongate"));
+}
+
+#[test]
+fn privsep_malformed() {
+    let (parent, mut child) = privsep::socketpair().unwrap();
+    child.write_all(&[privsep::VERSION, 9, 4, 32, 0, 0, 0, 0, 8, 254, 73, 28,
+                      0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+
+    let mut blocker = Memory::new();
+    blocker.ensure_table("irongate").unwrap();
+    let e = gate().serve(&mut blocker, Server::new(parent), &Lockout::new()).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    assert_eq!(Some(&[][..]), blocker.table("irongate"));
+}
+
+fn gate<'a>() -> IronGate<'a> {
+    IronGate {
+        rules:        Rules {
+            limit:        3,
+            prefix_limit: 0,
+            prefix4:      24,
+            prefix6:      64,
+            period:       Duration::minutes(1),
+        },
+        shadow:       None,
+        dry_run:      false,
+        kill_states:  false,
+        resync:       false,
+        keep_active:  0,
+        batch_size:   1,
+        batch_delay:  Duration::zero(),
+        max_entries:  0,
+        evict:        Policy::Oldest,
+        allow:        Allowlist::new(),
+        ban_times:    vec![Some(Duration::minutes(10))],
+        decay:        Duration::days(1),
+        store:        None,
+        table_file:   None,
+        backend:      Backend::Pf(None, pf::PFR_TFLAG_PERSIST),
+        privsep:      None,
+        sandbox:      false,
+        table:        "irongate",
+    }
+}
+
+fn args(argv: &[&str]) -> Args {
+    let argv = Some("irongate").into_iter().chain(argv.iter().cloned());
+    Args::docopt().argv(argv).decode().unwrap()
+}
+
+fn log(event: &str) -> String {
+    format!("{} host {}", Local::now().format("%b %e %T"), event)
+}
+
+fn cidr(s: &str) -> Cidr {
+    Cidr::host(s.parse().unwrap())
+}